@@ -1,9 +1,15 @@
+use clap::builder::FalseyValueParser;
 use clap::{Parser, Subcommand, ValueEnum};
 use crossterm;
 
 use crate::common::format_version_to_display;
-use crate::input::{InitOptions, SizeOption};
+use crate::export::ExportFormat;
+use crate::heatmap::{HeatmapFormat, Layer};
+use crate::input::{Grid, InitOptions, SizeOption};
 use crate::pipeline::Pipeline;
+use crate::recording::Compression;
+use crate::snapshot::{parse_tick, SnapshotFormat, Tick};
+use crate::throttle::parse_speed;
 
 const DEFAULT_UNIX_SOCKET_PATH: &str = "/tmp/snakepipe.sock";
 const DEFAULT_TCP_PORT: &str = "8050";
@@ -15,6 +21,9 @@ const DEFAULT_TCP_HOST: &str = "127.0.0.1";
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+    /// Report on stderr the lines of the input that couldn't be parsed (line number, error and text)
+    #[arg(long, global = true, env = "SNAKEPIPE_STRICT", value_parser = FalseyValueParser::new())]
+    pub strict: bool,
 }
 
 const DEFAULT_WIDTH: u32 = 25;
@@ -39,23 +48,173 @@ pub enum Commands {
         /// Adjust size of the game to the size of your terminal
         #[arg(long, default_value_t = false)]
         fit_terminal: bool,
+        /// Battle mode: the playable area shrinks by one ring of cells every `shrink_every` ticks
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        shrink_every: Option<u32>,
+        /// Number of pairs of portals randomly placed on the board
+        #[arg(long, default_value_t = 0)]
+        portals: u32,
+        /// Json file describing the board (portals)
+        #[arg(long, conflicts_with = "ghost")]
+        map: Option<String>,
+        /// Number of snakes controlled by the cpu competing for the fruit (colliding with them kills you)
+        #[arg(long, default_value_t = 0)]
+        rivals: u32,
+        /// Power-ups randomly show up on the board: slow-motion, ghost (pass through your tail) and magnet (attracts the fruit)
+        #[arg(long, default_value_t = false)]
+        power_ups: bool,
+        /// Play on a hexagonal board (left/right arrows turn relative to the heading of the snake)
+        #[arg(long, default_value_t = false)]
+        hex: bool,
+        /// Seed of the random placements (recorded in the header): the same seed with the same options gives the same board
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Race against the first run of a recording, replayed in lockstep (on its board, with its options and its seed if recorded)
+        #[arg(long)]
+        ghost: Option<String>,
+        /// Also record the game into a file (compressed if it ends with .gz or .zst)
+        #[arg(long)]
+        output: Option<String>,
+        /// Compression of the recording (overrides the extension of `output`)
+        #[arg(long, value_enum, requires = "output")]
+        compress: Option<Compression>,
     },
     /// Reads gamestate from stdin and renders the game on your terminal
-    Render,
+    Render {
+        /// Read from a recording instead of stdin (gzip and zstd are decompressed)
+        #[arg(long)]
+        input: Option<String>,
+    },
     /// Reads stdin line by line and outputs each line on stdout each `frame_duration` ms (usefull for replaying a file)
     Throttle {
-        /// in ms
-        #[arg(long, default_value_t = 120)]
-        frame_duration: u32,
+        /// in ms - replay at a fixed frame rate (by default, the timing of the recording is reproduced, falling back to the `frameDuration` of its header)
+        #[arg(long)]
+        frame_duration: Option<u32>,
+        /// in ms - shorten the pauses of the recording longer than this
+        #[arg(long, conflicts_with = "frame_duration")]
+        max_pause: Option<u32>,
         /// Loop when at the beginning of the stream when it ends
         #[arg(long)]
         loop_infinite: bool,
+        /// Replay speed, like 0.5x or 2x (between 1/16x and 16x)
+        #[arg(long, default_value = "1x", value_parser = parse_speed)]
+        speed: f64,
+        /// Control the replay with the keyboard: space to pause, arrows to step, +/- to change the speed, digits to jump to 10% … 90% (reads the whole input first)
+        #[arg(long)]
+        interactive: bool,
+        /// Read from a recording instead of stdin (gzip and zstd are decompressed)
+        #[arg(long)]
+        input: Option<String>,
+        /// Also record the output into a file (compressed if it ends with .gz or .zst)
+        #[arg(long)]
+        output: Option<String>,
+        /// Compression of the recording (overrides the extension of `output`)
+        #[arg(long, value_enum, requires = "output")]
+        compress: Option<Compression>,
+    },
+    /// Reads gamestate from stdin and outputs it in a compact binary format (for recordings)
+    Encode,
+    /// Reads a recording in the binary format from stdin and outputs it as gamestate (NDJSON)
+    Decode,
+    /// Edits recordings: concatenates them, keeps a range of ticks, drops the paused stretches, reverses them (outputs a single recording)
+    Edit {
+        /// Recording to edit, repeat to concatenate several of them (they must share the same board) - stdin if none
+        #[arg(long)]
+        input: Vec<String>,
+        /// First tick (index of the game, starting at 0) to keep
+        #[arg(long)]
+        from_tick: Option<usize>,
+        /// Last tick to keep (included)
+        #[arg(long)]
+        to_tick: Option<usize>,
+        /// Drop the games while paused (and the time spent paused)
+        #[arg(long)]
+        drop_paused: bool,
+        /// Reverse the order of the games
+        #[arg(long)]
+        reverse: bool,
+    },
+    /// Reports the statistics of a recording: duration, ticks, scores, fruits eaten, turns, time paused, restarts and causes of death
+    Stats {
+        /// Recording to analyse (gzip and zstd are decompressed) - stdin if none
+        recording: Option<String>,
+        /// Output as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Shows how many times each cell was visited by the head of the snake or got a fruit
+    Heatmap {
+        /// Recording to analyse (gzip and zstd are decompressed) - stdin if none
+        recording: Option<String>,
+        #[arg(long, value_enum, default_value_t = HeatmapFormat::Terminal)]
+        format: HeatmapFormat,
+        /// Counts to show (the csv has both)
+        #[arg(long, value_enum, default_value_t = Layer::Head)]
+        layer: Layer,
+        /// Size of a cell in pixels (png)
+        #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u32).range(1..=256))]
+        cell_size: u32,
+        /// Write to a file instead of stdout
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Exports a recording to another format, timed like `throttle` replays it
+    Export {
+        /// Recording to export (gzip and zstd are decompressed) - stdin if none
+        recording: Option<String>,
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+        /// Write to a file instead of stdout
+        #[arg(long)]
+        output: Option<String>,
+        /// Speed of the export (like 0.5x, 1x or 2x)
+        #[arg(long, default_value = "1x", value_parser = parse_speed)]
+        speed: f64,
+        /// First tick (index of the game, starting at 0) to export
+        #[arg(long)]
+        from_tick: Option<usize>,
+        /// Last tick to export (included)
+        #[arg(long)]
+        to_tick: Option<usize>,
+        /// Size of a cell in pixels (gif and apng)
+        #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u32).range(1..=256))]
+        cell_size: u32,
+        /// Only the frames where something happened: turn, fruit eaten, pause, death … (csv)
+        #[arg(long)]
+        events_only: bool,
+    },
+    /// Renders a single frame of a recording as plain text or as an SVG image
+    Snapshot {
+        /// Recording to read (gzip and zstd are decompressed) - stdin if none
+        recording: Option<String>,
+        /// Index of the game (starting at 0), `last` or `game-over` (the first one)
+        #[arg(long, default_value = "last", value_parser = parse_tick)]
+        tick: Tick,
+        #[arg(long, value_enum, default_value_t = SnapshotFormat::Txt)]
+        format: SnapshotFormat,
+        /// Size of a cell in pixels (svg)
+        #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u32).range(1..=256))]
+        cell_size: u32,
+        /// Write to a file instead of stdout
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Prints the JSON Schema of the lines of the stream (generated from the Rust types)
+    Schema,
+    /// Checks each line of the stream against the JSON Schema and reports the violations
+    Validate {
+        /// Read from a recording instead of stdin (gzip and zstd are decompressed)
+        #[arg(long)]
+        input: Option<String>,
     },
     /// Renders the game in your browser by spawning a server and sending stdin via server-sent events to a JavaScript renderer
     RenderBrowser {
         /// Override port (default 8080)
         #[arg(long, default_value_t = 8080)]
         port: u16,
+        /// Read from a recording instead of stdin (gzip and zstd are decompressed)
+        #[arg(long)]
+        input: Option<String>,
     },
     /// Connects to the server spawned by `render-browser` and streams server-sent events back to the terminal
     StreamSse {
@@ -68,6 +227,9 @@ pub enum Commands {
         /// Unix socket file path
         #[arg(long, default_value = DEFAULT_UNIX_SOCKET_PATH)]
         path: String,
+        /// Read from a recording instead of stdin (gzip and zstd are decompressed)
+        #[arg(long)]
+        input: Option<String>,
     },
     /// Reads gamestate from a unix socket
     SocketWatch {
@@ -83,6 +245,9 @@ pub enum Commands {
         /// Tcp host
         #[arg(long, default_value = DEFAULT_TCP_HOST)]
         host: String,
+        /// Read from a recording instead of stdin (gzip and zstd are decompressed)
+        #[arg(long)]
+        input: Option<String>,
     },
     /// Reads gamestate from a tcp socket
     TcpWatch {
@@ -128,6 +293,12 @@ pub struct CliOptions<'a> {
     pub height: &'a Option<u32>,
     pub snake_length: &'a u32,
     pub fit_terminal: &'a bool,
+    pub shrink_every: &'a Option<u32>,
+    pub portals: &'a u32,
+    pub rivals: &'a u32,
+    pub power_ups: &'a bool,
+    pub hex: &'a bool,
+    pub seed: &'a Option<u64>,
 }
 
 impl Into<InitOptions> for CliOptions<'_> {
@@ -146,9 +317,11 @@ impl Into<InitOptions> for CliOptions<'_> {
         } else if self.fit_terminal.eq(&true) {
             let (width, height) = crossterm::terminal::size()
                 .unwrap_or((DEFAULT_WIDTH as u16 + 2, DEFAULT_HEIGHT as u16 + 6));
+            // hex cells are separated by a space
+            let cell_width = if *self.hex { 2 } else { 1 };
             size = SizeOption {
-                width: width as u32 - 2,   // 2 borders
-                height: height as u32 - 6, // 2 borders + 4 lines of score/etc ...
+                width: (width as u32 - 2) / cell_width, // 2 borders
+                height: height as u32 - 6,              // 2 borders + 4 lines of score/etc ...
             }
         } else {
             size = SizeOption {
@@ -158,13 +331,27 @@ impl Into<InitOptions> for CliOptions<'_> {
         }
         let mut features_with_version = std::collections::HashMap::new();
         features_with_version.insert("gamestate".to_string(), format_version_to_display());
-        let metadatas = std::collections::HashMap::new();
+        let mut metadatas = std::collections::HashMap::new();
+        if let Some(shrink_every) = self.shrink_every {
+            metadatas.insert("shrink-every".to_string(), shrink_every.to_string());
+        }
+        if *self.rivals > 0 {
+            metadatas.insert("rivals".to_string(), self.rivals.to_string());
+        }
         return InitOptions {
             frame_duration: *self.frame_duration,
             snake_length: *self.snake_length,
+            shrink_every: *self.shrink_every,
+            portals: *self.portals,
+            map_portals: Vec::new(),
+            rivals: *self.rivals,
+            power_ups: *self.power_ups,
             size,
+            grid: if *self.hex { Grid::Hex } else { Grid::Square },
+            seed: *self.seed,
             features_with_version: features_with_version,
             metadatas,
+            extra: serde_json::Map::new(),
         };
     }
 }
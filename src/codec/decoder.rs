@@ -0,0 +1,256 @@
+use std::io::{Error, ErrorKind, Read, Result};
+
+use serde_json::{json, Map, Value};
+
+use crate::codec::encoder::moved_tail;
+use crate::codec::varint::{read_bytes, read_signed, read_u8, read_varint};
+use crate::codec::{
+    merge_extras, DIRECTIONS, FLAG_DIRECTION, FLAG_ELAPSED, FLAG_EXTRAS, FLAG_FRUIT, FLAG_GREW,
+    FLAG_MOVED, FLAG_SCORE, FLAG_STATE, MAGIC, MIN_VERSION, STATES, TAG_DELTA, TAG_HEADER,
+    TAG_KEYFRAME, VERSION,
+};
+use crate::input::{Direction, Frame, Game, GameState, InitOptions, Position, Snake};
+
+/// Reads [`Frame`]s from the binary format, see [`crate::codec`]
+///
+/// The first frame is the header of the first session.
+/// Iterating stops on the first error (the stream can't be resynchronised).
+pub struct Decoder<R: Read> {
+    reader: R,
+    /// version of the format of the recording being read
+    version: u8,
+    /// last game read in the current session (deltas are applied to it), along with its extras
+    previous: Option<(Game, Vec<u8>)>,
+    failed: bool,
+}
+
+impl<R: Read> Decoder<R> {
+    /// Checks the magic header right away
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 5];
+        reader.read_exact(&mut magic)?;
+        if !magic.starts_with(MAGIC) {
+            return Err(invalid_data("Not a snakepipe binary recording"));
+        }
+        check_version(magic[4])?;
+        Ok(Self {
+            reader,
+            version: magic[4],
+            previous: None,
+            failed: false,
+        })
+    }
+
+    /// `None` at the end of the stream
+    pub fn read_frame(&mut self) -> Result<Option<Frame>> {
+        let mut tag = [0u8];
+        if self.reader.read(&mut tag)? == 0 {
+            return Ok(None);
+        }
+        let frame = match tag[0] {
+            TAG_HEADER => {
+                let options: InitOptions = serde_json::from_slice(&read_bytes(&mut self.reader)?)?;
+                self.previous = None;
+                Frame::Options(options)
+            }
+            TAG_KEYFRAME => {
+                let game = self.read_keyframe()?;
+                Frame::Game(game)
+            }
+            TAG_DELTA => {
+                let game = self.read_delta()?;
+                Frame::Game(game)
+            }
+            // concatenated recordings (the header that follows starts a new session)
+            tag if tag == MAGIC[0] => {
+                let mut magic = [0u8; 4];
+                self.reader.read_exact(&mut magic)?;
+                if magic[..3] != MAGIC[1..] {
+                    return Err(invalid_data(
+                        "Invalid magic header in the middle of the stream",
+                    ));
+                }
+                check_version(magic[3])?;
+                self.version = magic[3];
+                return self.read_frame();
+            }
+            tag => return Err(invalid_data(&format!("Unknown record {:#04x}", tag))),
+        };
+        Ok(Some(frame))
+    }
+
+    fn read_keyframe(&mut self) -> Result<Game> {
+        let reader = &mut self.reader;
+        let direction = read_direction(reader)?;
+        let head = read_position(reader)?;
+        let tail_len = read_varint(reader)?;
+        let mut tail: Vec<Position> = Vec::new();
+        let mut previous_fragment = head.clone();
+        for _ in 0..tail_len {
+            let fragment = Position {
+                x: previous_fragment.x + read_signed(reader)? as i32,
+                y: previous_fragment.y + read_signed(reader)? as i32,
+            };
+            tail.push(fragment.clone());
+            previous_fragment = fragment;
+        }
+        let fruit = read_position(reader)?;
+        let score = read_varint(reader)? as u32;
+        let state = read_state(reader)?;
+        let elapsed = if self.version >= 2 {
+            read_varint(reader)?.checked_sub(1)
+        } else {
+            None
+        };
+        let extras = read_bytes(reader)?;
+        let snake = Snake {
+            direction,
+            head,
+            tail,
+        };
+        let game = build_game(snake, fruit, score, state, elapsed, &extras)?;
+        self.previous = Some((game.clone(), extras));
+        Ok(game)
+    }
+
+    fn read_delta(&mut self) -> Result<Game> {
+        let (previous, previous_extras) = self
+            .previous
+            .take()
+            .ok_or_else(|| invalid_data("Delta record without a previous keyframe"))?;
+        let reader = &mut self.reader;
+        let flags = read_varint(reader)?;
+        let direction = if flags & FLAG_DIRECTION != 0 {
+            read_direction(reader)?
+        } else {
+            previous.snake.direction
+        };
+        let (head, tail) = if flags & FLAG_MOVED != 0 {
+            let head = Position {
+                x: previous.snake.head.x + read_signed(reader)? as i32,
+                y: previous.snake.head.y + read_signed(reader)? as i32,
+            };
+            let tail = moved_tail(
+                &previous.snake.head,
+                &previous.snake.tail,
+                flags & FLAG_GREW != 0,
+            );
+            (head, tail)
+        } else {
+            (previous.snake.head, previous.snake.tail)
+        };
+        let fruit = if flags & FLAG_FRUIT != 0 {
+            read_position(reader)?
+        } else {
+            previous.fruit
+        };
+        let score = if flags & FLAG_SCORE != 0 {
+            read_varint(reader)? as u32
+        } else {
+            previous.score
+        };
+        let state = if flags & FLAG_STATE != 0 {
+            read_state(reader)?
+        } else {
+            previous.state
+        };
+        let elapsed = if flags & FLAG_ELAPSED != 0 {
+            let previous_elapsed = previous
+                .elapsed
+                .ok_or_else(|| invalid_data("Elapsed delta without a previous elapsed"))?;
+            Some((previous_elapsed as i64 + read_signed(reader)?) as u64)
+        } else {
+            previous.elapsed
+        };
+        let extras = if flags & FLAG_EXTRAS != 0 {
+            read_bytes(reader)?
+        } else {
+            previous_extras
+        };
+        let snake = Snake {
+            direction,
+            head,
+            tail,
+        };
+        let game = build_game(snake, fruit, score, state, elapsed, &extras)?;
+        self.previous = Some((game.clone(), extras));
+        Ok(game)
+    }
+}
+
+impl<R: Read> Iterator for Decoder<R> {
+    type Item = Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.read_frame() {
+            Ok(frame) => frame.map(Ok),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn check_version(version: u8) -> Result<()> {
+    if (MIN_VERSION..=VERSION).contains(&version) {
+        Ok(())
+    } else {
+        Err(invalid_data(&format!(
+            "Unsupported binary format version {}",
+            version
+        )))
+    }
+}
+
+fn read_position<R: Read>(reader: &mut R) -> Result<Position> {
+    Ok(Position {
+        x: read_signed(reader)? as i32,
+        y: read_signed(reader)? as i32,
+    })
+}
+
+fn read_direction<R: Read>(reader: &mut R) -> Result<Direction> {
+    let byte = read_u8(reader)?;
+    DIRECTIONS
+        .get(byte as usize)
+        .copied()
+        .ok_or_else(|| invalid_data(&format!("Unknown direction {}", byte)))
+}
+
+fn read_state<R: Read>(reader: &mut R) -> Result<GameState> {
+    let byte = read_u8(reader)?;
+    STATES
+        .get(byte as usize)
+        .cloned()
+        .ok_or_else(|| invalid_data(&format!("Unknown state {}", byte)))
+}
+
+fn build_game(
+    snake: Snake,
+    fruit: Position,
+    score: u32,
+    state: GameState,
+    elapsed: Option<u64>,
+    extras: &[u8],
+) -> Result<Game> {
+    let core = json!({
+        "snake": snake,
+        "fruit": fruit,
+        "score": score,
+        "state": state,
+        "elapsed": elapsed,
+    });
+    let core: Map<String, Value> = match core {
+        Value::Object(core) => core,
+        _ => unreachable!(),
+    };
+    Ok(merge_extras(core, extras)?)
+}
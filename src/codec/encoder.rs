@@ -0,0 +1,172 @@
+use std::io::{Result, Write};
+
+use crate::codec::varint::{write_bytes, write_signed, write_varint};
+use crate::codec::{
+    direction_to_byte, extras, state_to_byte, FLAG_DIRECTION, FLAG_ELAPSED, FLAG_EXTRAS,
+    FLAG_FRUIT, FLAG_GREW, FLAG_MOVED, FLAG_SCORE, FLAG_STATE, MAGIC, TAG_DELTA, TAG_HEADER,
+    TAG_KEYFRAME, VERSION,
+};
+use crate::input::{Frame, Game, Position};
+
+/// Writes [`Frame`]s in the binary format, see [`crate::codec`]
+pub struct Encoder<W: Write> {
+    writer: W,
+    /// last game written in the current session (deltas are computed from it)
+    previous: Option<(Game, Vec<u8>)>,
+}
+
+impl<W: Write> Encoder<W> {
+    /// Writes the magic header right away
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        Ok(Self {
+            writer,
+            previous: None,
+        })
+    }
+
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        match frame {
+            Frame::Options(options) => {
+                self.writer.write_all(&[TAG_HEADER])?;
+                write_bytes(&mut self.writer, &serde_json::to_vec(options)?)?;
+                // a new session starts with a keyframe
+                self.previous = None;
+            }
+            Frame::Game(game) => {
+                let game_extras = extras(game);
+                match self.previous.take() {
+                    Some((previous, previous_extras)) => {
+                        match delta_flags(&previous, game, previous_extras != game_extras) {
+                            Some(flags) => {
+                                self.write_delta(flags, &previous, game, &game_extras)?
+                            }
+                            None => self.write_keyframe(game, &game_extras)?,
+                        }
+                    }
+                    None => self.write_keyframe(game, &game_extras)?,
+                }
+                self.previous = Some((game.clone(), game_extras));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_keyframe(&mut self, game: &Game, game_extras: &[u8]) -> Result<()> {
+        let writer = &mut self.writer;
+        writer.write_all(&[TAG_KEYFRAME, direction_to_byte(game.snake.direction)])?;
+        write_position(writer, &game.snake.head)?;
+        write_varint(writer, game.snake.tail.len() as u64)?;
+        let mut previous_fragment = &game.snake.head;
+        for fragment in game.snake.tail.iter() {
+            write_signed(writer, (fragment.x - previous_fragment.x) as i64)?;
+            write_signed(writer, (fragment.y - previous_fragment.y) as i64)?;
+            previous_fragment = fragment;
+        }
+        write_position(writer, &game.fruit)?;
+        write_varint(writer, game.score as u64)?;
+        writer.write_all(&[state_to_byte(&game.state)])?;
+        write_varint(writer, game.elapsed.map_or(0, |elapsed| elapsed + 1))?;
+        write_bytes(writer, game_extras)
+    }
+
+    fn write_delta(
+        &mut self,
+        flags: u64,
+        previous: &Game,
+        game: &Game,
+        game_extras: &[u8],
+    ) -> Result<()> {
+        let writer = &mut self.writer;
+        writer.write_all(&[TAG_DELTA])?;
+        write_varint(writer, flags)?;
+        if flags & FLAG_DIRECTION != 0 {
+            writer.write_all(&[direction_to_byte(game.snake.direction)])?;
+        }
+        if flags & FLAG_MOVED != 0 {
+            write_signed(writer, (game.snake.head.x - previous.snake.head.x) as i64)?;
+            write_signed(writer, (game.snake.head.y - previous.snake.head.y) as i64)?;
+        }
+        if flags & FLAG_FRUIT != 0 {
+            write_position(writer, &game.fruit)?;
+        }
+        if flags & FLAG_SCORE != 0 {
+            write_varint(writer, game.score as u64)?;
+        }
+        if flags & FLAG_STATE != 0 {
+            writer.write_all(&[state_to_byte(&game.state)])?;
+        }
+        if flags & FLAG_ELAPSED != 0 {
+            let (elapsed, previous_elapsed) = (game.elapsed.unwrap(), previous.elapsed.unwrap());
+            write_signed(writer, elapsed as i64 - previous_elapsed as i64)?;
+        }
+        if flags & FLAG_EXTRAS != 0 {
+            write_bytes(writer, game_extras)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_position<W: Write>(writer: &mut W, position: &Position) -> Result<()> {
+    write_signed(writer, position.x as i64)?;
+    write_signed(writer, position.y as i64)
+}
+
+/// The tail after the head moved from `head` (`grew` when the last fragment is kept)
+pub(super) fn moved_tail(head: &Position, tail: &[Position], grew: bool) -> Vec<Position> {
+    if tail.is_empty() && !grew {
+        return Vec::new();
+    }
+    let kept = if grew { tail.len() } else { tail.len() - 1 };
+    std::iter::once(head.clone())
+        .chain(tail[..kept].iter().cloned())
+        .collect()
+}
+
+/// Flags of the delta from `previous` to `game`, `None` if the snake can't be described as a move (needs a keyframe)
+fn delta_flags(previous: &Game, game: &Game, extras_changed: bool) -> Option<u64> {
+    let mut flags = 0;
+    if game.snake.direction != previous.snake.direction {
+        flags |= FLAG_DIRECTION;
+    }
+    if game.snake.head != previous.snake.head || game.snake.tail != previous.snake.tail {
+        let (head, tail) = (&previous.snake.head, &previous.snake.tail);
+        if game.snake.tail == moved_tail(head, tail, false) {
+            flags |= FLAG_MOVED;
+        } else if game.snake.tail == moved_tail(head, tail, true) {
+            flags |= FLAG_MOVED | FLAG_GREW;
+        } else {
+            return None;
+        }
+    }
+    if game.fruit != previous.fruit {
+        flags |= FLAG_FRUIT;
+    }
+    if game.score != previous.score {
+        flags |= FLAG_SCORE;
+    }
+    if game.state != previous.state {
+        flags |= FLAG_STATE;
+    }
+    match (previous.elapsed, game.elapsed) {
+        (Some(previous_elapsed), Some(elapsed)) if elapsed != previous_elapsed => {
+            flags |= FLAG_ELAPSED
+        }
+        (Some(_), Some(_)) | (None, None) => {}
+        // elapsed appeared/disappeared: only a keyframe can describe it
+        _ => return None,
+    }
+    if extras_changed {
+        flags |= FLAG_EXTRAS;
+    }
+    Some(flags)
+}
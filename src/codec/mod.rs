@@ -0,0 +1,230 @@
+//! Compact binary recording format (`snakepipe encode` / `snakepipe decode`)
+//!
+//! Instead of repeating the whole state of the game on each line like the NDJSON format,
+//! only what changed since the previous frame is recorded.
+//!
+//! ```text
+//! magic     "SNKP" followed by the version of the format (1 byte)
+//! record    tag (1 byte) followed by its payload
+//!  header   0x01 | length + InitOptions as json - starts a session, like the first line of the NDJSON format
+//!  keyframe 0x02 | direction, head, tail, fruit, score, state, elapsed, extras - the whole Game
+//!  delta    0x03 | flags + the fields flagged as changed since the previous Game
+//! ```
+//!
+//! Integers are varints (LEB128), signed ones (positions) are zigzag encoded first.
+//! The tail is recorded as moves from one fragment to the next, so in a delta,
+//! a move of the snake is only the move of the head (plus a flag if it grew).
+//! `elapsed` is recorded plus one (0 when missing), deltas record how much it increased.
+//! Version 1 didn't record it (it was part of the extras), it can still be decoded.
+//! Extras are the fields of [`Game`] other than the snake, fruit, score and state (rivals, portals ...),
+//! recorded as json, only when they change.
+
+mod decoder;
+mod encoder;
+mod varint;
+
+use std::io::{BufWriter, Write};
+
+use serde_json::{Map, Value};
+
+pub use crate::codec::decoder::Decoder;
+pub use crate::codec::encoder::Encoder;
+use crate::input::{parse_gamestate_with, Direction, Frame, Game, GameState, ParseOptions};
+
+pub const MAGIC: &[u8; 4] = b"SNKP";
+pub const VERSION: u8 = 2;
+/// Oldest version that can still be decoded
+pub const MIN_VERSION: u8 = 1;
+
+const TAG_HEADER: u8 = 0x01;
+const TAG_KEYFRAME: u8 = 0x02;
+const TAG_DELTA: u8 = 0x03;
+
+const FLAG_DIRECTION: u64 = 1;
+const FLAG_MOVED: u64 = 1 << 1;
+const FLAG_GREW: u64 = 1 << 2;
+const FLAG_FRUIT: u64 = 1 << 3;
+const FLAG_SCORE: u64 = 1 << 4;
+const FLAG_STATE: u64 = 1 << 5;
+const FLAG_EXTRAS: u64 = 1 << 6;
+const FLAG_ELAPSED: u64 = 1 << 7;
+
+const DIRECTIONS: [Direction; 8] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+    Direction::UpLeft,
+    Direction::UpRight,
+    Direction::DownLeft,
+    Direction::DownRight,
+];
+
+const STATES: [GameState; 3] = [GameState::Paused, GameState::Over, GameState::Running];
+
+/// Fields of [`Game`] that are recorded in binary, the others are part of the extras
+const CORE_FIELDS: [&str; 5] = ["snake", "fruit", "score", "state", "elapsed"];
+
+/// true if `buffer` (the beginning of a stream) is in the binary format
+pub fn is_binary(buffer: &[u8]) -> bool {
+    buffer.starts_with(MAGIC)
+}
+
+fn direction_to_byte(direction: Direction) -> u8 {
+    DIRECTIONS
+        .iter()
+        .position(|candidate| *candidate == direction)
+        .unwrap() as u8
+}
+
+fn state_to_byte(state: &GameState) -> u8 {
+    STATES
+        .iter()
+        .position(|candidate| candidate == state)
+        .unwrap() as u8
+}
+
+/// The fields of `game` other than [`CORE_FIELDS`], as json (empty if there are none)
+fn extras(game: &Game) -> Vec<u8> {
+    match serde_json::to_value(game) {
+        Ok(Value::Object(fields)) => {
+            // filtered rather than removed, to keep the order of the remaining fields
+            let fields: Map<String, Value> = fields
+                .into_iter()
+                .filter(|(field, _)| !CORE_FIELDS.contains(&field.as_str()))
+                .collect();
+            if fields.is_empty() {
+                Vec::new()
+            } else {
+                serde_json::to_vec(&fields).unwrap()
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Reads stdin (NDJSON or binary) and outputs the binary format on stdout
+pub fn run_encode(parse_options: ParseOptions) {
+    match parse_gamestate_with(parse_options) {
+        Ok(input) => {
+            let stdout = BufWriter::new(std::io::stdout().lock());
+            let result = Encoder::new(stdout).and_then(|mut encoder| {
+                encoder.write_frame(&Frame::Options(input.options))?;
+                for frame in input.lines {
+                    encoder.write_frame(&frame)?;
+                    // flush on each frame, so that a live game can be encoded
+                    encoder.flush()?;
+                }
+                encoder.flush()
+            });
+            if let Err(e) = result {
+                eprintln!("Error occurred while writing to stdout: \"{}\"", e);
+                std::process::exit(exitcode::IOERR);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error occurred while parsing stdin: \"{}\"", e);
+            std::process::exit(exitcode::DATAERR);
+        }
+    }
+}
+
+/// Reads stdin (binary or NDJSON) and outputs NDJSON on stdout
+pub fn run_decode(parse_options: ParseOptions) {
+    match parse_gamestate_with(parse_options) {
+        Ok(input) => {
+            let mut stdout = std::io::stdout().lock();
+            println!("{}\r", serde_json::to_string(&input.options).unwrap());
+            for frame in input.lines {
+                if writeln!(stdout, "{}\r", serde_json::to_string(&frame).unwrap()).is_err() {
+                    std::process::exit(exitcode::IOERR);
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Error occurred while parsing stdin: \"{}\"", e);
+            std::process::exit(exitcode::DATAERR);
+        }
+    }
+}
+
+/// Merges the extras recorded with [`extras`] back into the core fields of a [`Game`]
+fn merge_extras(mut core: Map<String, Value>, extras: &[u8]) -> serde_json::Result<Game> {
+    if !extras.is_empty() {
+        let extras: Map<String, Value> = serde_json::from_slice(extras)?;
+        core.extend(extras);
+    }
+    serde_json::from_value(Value::Object(core))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(bytes: &[u8]) -> Vec<String> {
+        Decoder::new(bytes)
+            .unwrap()
+            .map(|frame| serde_json::to_string(&frame.unwrap()).unwrap())
+            .collect()
+    }
+
+    fn encode_all(lines: &[&str]) -> Vec<u8> {
+        let mut encoder = Encoder::new(Vec::new()).unwrap();
+        for line in lines {
+            let frame = match serde_json::from_str::<Game>(line) {
+                Ok(game) => Frame::Game(game),
+                Err(_) => Frame::Options(serde_json::from_str(line).unwrap()),
+            };
+            encoder.write_frame(&frame).unwrap();
+        }
+        encoder.into_inner()
+    }
+
+    const HEADER: &str = r#"{"frameDuration":120,"size":{"width":25,"height":25},"featuresWithVersion":{"gamestate":"snakepipe@2.2.1(rust)"},"metadatas":{}}"#;
+
+    #[test]
+    fn should_roundtrip_moves_growth_fruit_and_elapsed_changes() {
+        let lines = [
+            HEADER,
+            r#"{"snake":{"direction":"down","head":{"x":3,"y":3},"tail":[{"x":3,"y":2},{"x":3,"y":1}]},"fruit":{"x":5,"y":4},"score":0,"state":"running","elapsed":0}"#,
+            r#"{"snake":{"direction":"right","head":{"x":4,"y":3},"tail":[{"x":3,"y":3},{"x":3,"y":2}]},"fruit":{"x":5,"y":4},"score":0,"state":"running","elapsed":120}"#,
+            r#"{"snake":{"direction":"right","head":{"x":4,"y":3},"tail":[{"x":3,"y":3},{"x":3,"y":2}]},"fruit":{"x":5,"y":4},"score":0,"state":"paused"}"#,
+            r#"{"snake":{"direction":"right","head":{"x":5,"y":3},"tail":[{"x":4,"y":3},{"x":3,"y":3},{"x":3,"y":2}]},"fruit":{"x":0,"y":9},"score":1,"state":"running","elapsed":360}"#,
+            r#"{"snake":{"direction":"left","head":{"x":24,"y":3},"tail":[{"x":5,"y":3},{"x":4,"y":3},{"x":3,"y":3}]},"fruit":{"x":0,"y":9},"score":1,"state":"over","elapsed":480}"#,
+        ];
+        let bytes = encode_all(&lines);
+        assert!(is_binary(&bytes));
+        assert_eq!(decode_all(&bytes), lines);
+    }
+
+    #[test]
+    fn should_roundtrip_extras_unknown_fields_and_new_sessions() {
+        let lines = [
+            HEADER,
+            r#"{"snake":{"direction":"down","head":{"x":3,"y":3},"tail":[]},"fruit":{"x":5,"y":4},"score":0,"state":"running","portals":[[{"x":1,"y":1},{"x":8,"y":8}]]}"#,
+            r#"{"snake":{"direction":"down","head":{"x":3,"y":4},"tail":[]},"fruit":{"x":5,"y":4},"score":0,"state":"running","portals":[[{"x":1,"y":1},{"x":8,"y":8}]]}"#,
+            r#"{"snake":{"direction":"down","head":{"x":3,"y":5},"tail":[]},"fruit":{"x":5,"y":4},"score":0,"state":"running","zLives":3,"combo":{"y":1,"x":2}}"#,
+            HEADER,
+            r#"{"snake":{"direction":"up","head":{"x":0,"y":0},"tail":[{"x":0,"y":1}]},"fruit":{"x":2,"y":2},"score":0,"state":"running"}"#,
+        ];
+        assert_eq!(decode_all(&encode_all(&lines)), lines);
+    }
+
+    #[test]
+    fn should_be_smaller_than_ndjson() {
+        let mut lines = vec![HEADER.to_string()];
+        for x in 20..100 {
+            let tail: Vec<String> = (1..=20)
+                .map(|i| format!(r#"{{"x":{},"y":0}}"#, x - i))
+                .collect();
+            lines.push(format!(
+                r#"{{"snake":{{"direction":"right","head":{{"x":{},"y":0}},"tail":[{}]}},"fruit":{{"x":5,"y":4}},"score":0,"state":"running"}}"#,
+                x,
+                tail.join(",")
+            ));
+        }
+        let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+        let ndjson_len: usize = lines.iter().map(|line| line.len() + 1).sum();
+        assert!(encode_all(&lines).len() * 10 < ndjson_len);
+    }
+}
@@ -0,0 +1,57 @@
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+/// LEB128: 7 bits per byte, the high bit is set when more bytes follow
+pub fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+pub fn read_varint<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8(reader)?;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(Error::new(ErrorKind::InvalidData, "varint is too long"))
+}
+
+/// Zigzag encoding, so that small negative numbers (like moves to the left) stay small
+pub fn write_signed<W: Write>(writer: &mut W, value: i64) -> Result<()> {
+    write_varint(writer, ((value << 1) ^ (value >> 63)) as u64)
+}
+
+pub fn read_signed<R: Read>(reader: &mut R) -> Result<i64> {
+    let value = read_varint(reader)?;
+    Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+}
+
+pub fn read_u8<R: Read>(reader: &mut R) -> Result<u8> {
+    let mut byte = [0u8];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Length prefixed bytes
+pub fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<()> {
+    write_varint(writer, bytes.len() as u64)?;
+    writer.write_all(bytes)
+}
+
+pub fn read_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+    let len = read_varint(reader)?;
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "truncated record"));
+    }
+    Ok(bytes)
+}
@@ -109,6 +109,15 @@ pub fn format_metadatas(
             result.push("Record mode".to_string());
         }
     }
+    if let Some(value) = metadatas.get("shrink-every") {
+        result.push(format!("Shrinking arena every {} ticks", value));
+    }
+    if let Some(value) = metadatas.get("rivals") {
+        result.push(format!("{} cpu rivals", value));
+    }
+    if let Some(value) = metadatas.get("ghost") {
+        result.push(format!("Racing the ghost of {}", value));
+    }
     if let Some(value) = metadatas.get("render-browser-host") {
         result.push(format!("Mirrored on {}", value));
     }
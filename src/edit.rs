@@ -0,0 +1,371 @@
+//! Editing recordings (`snakepipe edit`)
+//!
+//! The edits are applied in this order:
+//! 1. the recordings are concatenated (they must share the same board)
+//! 2. only the ticks between `from_tick` and `to_tick` are kept (indexes of the games, starting at 0, both included)
+//! 3. the paused stretches are dropped
+//! 4. the games are reversed
+//!
+//! The output is a single session: one header, merging the ones of the recordings, then the games.
+//! `elapsed` is recomputed from the time between the games (the edits would otherwise break the timing of the replay).
+
+use std::io::Write;
+use std::path::Path;
+
+use crate::common::format_version_to_display;
+use crate::input::{parse_gamestate_from, Frame, Game, GameState, InitOptions, ParseOptions};
+use crate::recording::input_name;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EditOptions {
+    pub from_tick: Option<usize>,
+    pub to_tick: Option<usize>,
+    pub drop_paused: bool,
+    pub reverse: bool,
+}
+
+impl EditOptions {
+    /// What was done, noted in the `metadatas` of the header
+    fn describe(&self, recordings: usize) -> String {
+        let mut edits = Vec::new();
+        if recordings > 1 {
+            edits.push(format!("concat:{}", recordings));
+        }
+        if self.from_tick.is_some() || self.to_tick.is_some() {
+            edits.push(format!(
+                "trim:{}-{}",
+                self.from_tick
+                    .map_or(String::new(), |tick| tick.to_string()),
+                self.to_tick.map_or(String::new(), |tick| tick.to_string())
+            ));
+        }
+        if self.drop_paused {
+            edits.push("drop-paused".to_string());
+        }
+        if self.reverse {
+            edits.push("reverse".to_string());
+        }
+        edits.join(",")
+    }
+}
+
+/// A game along with the ms since the previous one in the recording (`None` if unknown)
+struct Tick {
+    game: Game,
+    gap: Option<u64>,
+}
+
+/// Merges `options` into `header`, which must describe the same board
+fn merge_header(header: &mut InitOptions, options: InitOptions) -> Result<(), String> {
+    if options.size.width != header.size.width
+        || options.size.height != header.size.height
+        || options.grid != header.grid
+    {
+        return Err(format!(
+            "board of {}x{} ({:?}), expected {}x{} ({:?})",
+            options.size.width,
+            options.size.height,
+            options.grid,
+            header.size.width,
+            header.size.height,
+            header.grid
+        ));
+    }
+    for (feature, version) in options.features_with_version {
+        header
+            .features_with_version
+            .entry(feature)
+            .or_insert(version);
+    }
+    for (key, value) in options.metadatas {
+        header.metadatas.entry(key).or_insert(value);
+    }
+    Ok(())
+}
+
+/// Appends the games of a recording to `ticks` (with the time since the previous game of the session),
+/// its headers being merged into `header`
+fn concat(
+    header: &mut Option<InitOptions>,
+    ticks: &mut Vec<Tick>,
+    frames: impl Iterator<Item = Frame>,
+) -> Result<(), String> {
+    let mut previous_elapsed: Option<u64> = None;
+    for frame in frames {
+        match frame {
+            Frame::Options(options) => {
+                previous_elapsed = None;
+                match header.as_mut() {
+                    Some(header) => merge_header(header, options)?,
+                    None => *header = Some(options),
+                }
+            }
+            Frame::Game(game) => {
+                let gap = previous_elapsed
+                    .zip(game.elapsed)
+                    .and_then(|(previous, elapsed)| elapsed.checked_sub(previous));
+                previous_elapsed = game.elapsed;
+                ticks.push(Tick { game, gap });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Trims, drops the paused stretches and reverses, then recomputes `elapsed` (missing gaps last `frame_duration`)
+fn edit(ticks: Vec<Tick>, edit_options: &EditOptions, frame_duration: u32) -> Vec<Tick> {
+    let from_tick = edit_options.from_tick.unwrap_or(0);
+    let to_tick = edit_options.to_tick.unwrap_or(usize::MAX);
+    let mut ticks: Vec<Tick> = ticks
+        .into_iter()
+        .enumerate()
+        .filter(|(tick, _)| (from_tick..=to_tick).contains(tick))
+        .map(|(_, tick)| tick)
+        .collect();
+    if edit_options.drop_paused {
+        let mut after_pause = false;
+        ticks.retain_mut(|tick| {
+            if tick.game.state == GameState::Paused {
+                after_pause = true;
+                return false;
+            }
+            // the game resumed: the time spent paused is dropped too
+            if after_pause {
+                tick.gap = None;
+                after_pause = false;
+            }
+            true
+        });
+    }
+    if edit_options.reverse {
+        // the time between two games now comes before the game that was preceding
+        let gaps: Vec<Option<u64>> = ticks.iter().map(|tick| tick.gap).collect();
+        ticks.reverse();
+        for (tick, gap) in ticks
+            .iter_mut()
+            .zip(std::iter::once(None).chain(gaps.into_iter().skip(1).rev()))
+        {
+            tick.gap = gap;
+        }
+    }
+
+    // recordings without timing are left as is
+    if ticks.iter().any(|tick| tick.game.elapsed.is_some()) {
+        let mut elapsed = 0;
+        for (index, tick) in ticks.iter_mut().enumerate() {
+            if index > 0 {
+                elapsed += tick.gap.unwrap_or(frame_duration as u64);
+            }
+            tick.game.elapsed = Some(elapsed);
+        }
+    }
+
+    ticks
+}
+
+/// Reads the recordings (stdin if there are none), applies the edits and outputs the result on stdout
+pub fn run(input_paths: &[&Path], edit_options: EditOptions, parse_options: ParseOptions) {
+    let input_paths: Vec<Option<&Path>> = if input_paths.is_empty() {
+        vec![None]
+    } else {
+        input_paths.iter().map(|path| Some(*path)).collect()
+    };
+    let mut header: Option<InitOptions> = None;
+    let mut ticks: Vec<Tick> = Vec::new();
+    for input_path in input_paths.iter().copied() {
+        let input = match parse_gamestate_from(input_path, parse_options) {
+            Ok(input) => input,
+            Err(e) => {
+                eprintln!(
+                    "Error occurred while parsing {}: \"{}\"",
+                    input_name(input_path),
+                    e
+                );
+                std::process::exit(exitcode::DATAERR);
+            }
+        };
+        let frames = std::iter::once(Frame::Options(input.options)).chain(input.lines);
+        if let Err(e) = concat(&mut header, &mut ticks, frames) {
+            eprintln!(
+                "Error: {} can't be concatenated, {}",
+                input_name(input_path),
+                e
+            );
+            std::process::exit(exitcode::DATAERR);
+        }
+    }
+    let mut header = header.unwrap();
+
+    let recorded_ticks = ticks.len();
+    let ticks = edit(ticks, &edit_options, header.frame_duration);
+    if ticks.is_empty() {
+        eprintln!(
+            "Nothing to output: no games left out of the {} ticks of {}",
+            recorded_ticks,
+            input_paths
+                .iter()
+                .map(|input_path| input_name(*input_path))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        std::process::exit(exitcode::DATAERR);
+    }
+
+    header
+        .features_with_version
+        .insert("edit".to_string(), format_version_to_display());
+    let description = edit_options.describe(input_paths.len());
+    if !description.is_empty() {
+        header.metadatas.insert("edited".to_string(), description);
+    }
+    let mut stdout = std::io::stdout().lock();
+    let lines = std::iter::once(serde_json::to_string(&header).unwrap()).chain(
+        ticks
+            .iter()
+            .map(|tick| serde_json::to_string(&tick.game).unwrap()),
+    );
+    for line in lines {
+        if writeln!(stdout, "{}", line).is_err() {
+            std::process::exit(exitcode::IOERR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = r#"{"frameDuration":120,"size":{"width":25,"height":25},"featuresWithVersion":{"gamestate":"snakepipe@2.2.1(rust)"},"metadatas":{}}"#;
+
+    fn header(width: u32) -> Frame {
+        let mut options: InitOptions = serde_json::from_str(HEADER).unwrap();
+        options.size.width = width;
+        Frame::Options(options)
+    }
+
+    /// A game told apart by the `x` of the head
+    fn game(x: i32, state: &str, elapsed: u64) -> Frame {
+        Frame::Game(
+            serde_json::from_str(&format!(
+                r#"{{"snake":{{"direction":"right","head":{{"x":{},"y":0}},"tail":[]}},"fruit":{{"x":0,"y":9}},"score":0,"state":"{}","elapsed":{}}}"#,
+                x, state, elapsed
+            ))
+            .unwrap(),
+        )
+    }
+
+    fn read(recordings: Vec<Vec<Frame>>) -> Vec<Tick> {
+        let mut header = None;
+        let mut ticks = Vec::new();
+        for frames in recordings {
+            concat(&mut header, &mut ticks, frames.into_iter()).unwrap();
+        }
+        ticks
+    }
+
+    /// `x` of the head and `elapsed` of each game
+    fn summary(ticks: &[Tick]) -> Vec<(i32, u64)> {
+        ticks
+            .iter()
+            .map(|tick| (tick.game.snake.head.x, tick.game.elapsed.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn should_move_the_gaps_along_when_reversing() {
+        let ticks = read(vec![vec![
+            header(25),
+            game(0, "running", 1000),
+            game(1, "running", 1100),
+            game(2, "running", 1300),
+        ]]);
+        let edit_options = EditOptions {
+            reverse: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            summary(&edit(ticks, &edit_options, 120)),
+            [(2, 0), (1, 200), (0, 300)]
+        );
+    }
+
+    #[test]
+    fn should_drop_the_time_spent_paused_within_the_trimmed_ticks() {
+        let ticks = read(vec![vec![
+            header(25),
+            game(0, "running", 0),
+            game(1, "running", 100),
+            game(2, "running", 200),
+            game(3, "paused", 300),
+            game(4, "running", 5000),
+            game(5, "running", 5100),
+            game(6, "running", 5200),
+        ]]);
+        let edit_options = EditOptions {
+            from_tick: Some(1),
+            to_tick: Some(5),
+            drop_paused: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            summary(&edit(ticks, &edit_options, 120)),
+            [(1, 0), (2, 100), (4, 220), (5, 320)]
+        );
+    }
+
+    #[test]
+    fn should_not_carry_the_gaps_over_from_one_recording_to_the_next() {
+        let ticks = read(vec![
+            vec![header(25), game(0, "running", 0), game(1, "running", 100)],
+            vec![
+                header(25),
+                game(2, "running", 9000),
+                game(3, "running", 9050),
+            ],
+        ]);
+        assert_eq!(
+            summary(&edit(ticks, &EditOptions::default(), 120)),
+            [(0, 0), (1, 100), (2, 220), (3, 270)]
+        );
+    }
+
+    #[test]
+    fn should_refuse_to_concat_other_boards() {
+        let mut header_options = None;
+        let mut ticks = Vec::new();
+        concat(
+            &mut header_options,
+            &mut ticks,
+            vec![header(25), game(0, "running", 0)].into_iter(),
+        )
+        .unwrap();
+        assert!(concat(
+            &mut header_options,
+            &mut ticks,
+            vec![header(30)].into_iter()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn should_keep_nothing_out_of_an_empty_range() {
+        let ticks = || {
+            read(vec![vec![
+                header(25),
+                game(0, "running", 0),
+                game(1, "running", 100),
+            ]])
+        };
+        let reversed_range = EditOptions {
+            from_tick: Some(1),
+            to_tick: Some(0),
+            ..Default::default()
+        };
+        let past_the_end = EditOptions {
+            from_tick: Some(2),
+            ..Default::default()
+        };
+        assert!(edit(ticks(), &reversed_range, 120).is_empty());
+        assert!(edit(ticks(), &past_the_end, 120).is_empty());
+    }
+}
@@ -0,0 +1,171 @@
+//! Animated images: GIF and APNG (`<https://wiki.mozilla.org/APNG_Specification>`), both looping forever
+
+use std::borrow::Cow;
+use std::io::{Error, Result, Write};
+use std::time::Duration;
+
+use crate::export::raster::{rasterize, Raster, PALETTE};
+use crate::export::Timeline;
+
+/// Browsers show the GIF frames of less than 2 centiseconds for 10 centiseconds
+const MIN_GIF_DELAY: u64 = 2;
+
+fn palette() -> Vec<u8> {
+    PALETTE.iter().flatten().copied().collect()
+}
+
+/// The rasters to show with their delays, rounded to a `unit`: the rounding errors don't add up over the frames
+///
+/// The frames shorter than `min` units are skipped, the next one shown starting earlier instead - the total
+/// duration is kept. The last frame (game over …) is always shown, taking the time it lacks from the ones before.
+fn delays(rasters: &[Raster], unit: Duration, min: u64) -> Vec<(&Raster, u64)> {
+    let units = |duration: Duration| (duration.as_secs_f64() / unit.as_secs_f64()).round() as u64;
+    let mut shown: Vec<(&Raster, u64)> = Vec::new();
+    let Some((last, others)) = rasters.split_last() else {
+        return shown;
+    };
+    let mut elapsed = Duration::ZERO;
+    // end of the last frame shown, in units
+    let mut start = 0;
+    for raster in others {
+        elapsed += raster.delay;
+        let end = units(elapsed);
+        if end - start >= min {
+            shown.push((raster, end - start));
+            start = end;
+        }
+    }
+    elapsed += last.delay;
+    let mut left = units(elapsed) - start;
+    while left < min {
+        match shown.last_mut() {
+            Some((_, delay)) if *delay >= 2 * min - left => {
+                *delay -= min - left;
+                left = min;
+            }
+            // too short to give some time away: skipped too
+            Some(_) => left += shown.pop().map_or(0, |(_, delay)| delay),
+            // the whole animation is shorter than `min`
+            None => left = min,
+        }
+    }
+    shown.push((last, left));
+    shown
+}
+
+pub(crate) fn write_gif<W: Write>(
+    timeline: &Timeline,
+    cell_size: u32,
+    writer: &mut W,
+) -> Result<()> {
+    let rasters = rasterize(timeline, cell_size);
+    let (width, height) = match (
+        u16::try_from(rasters[0].width),
+        u16::try_from(rasters[0].height),
+    ) {
+        (Ok(width), Ok(height)) => (width, height),
+        _ => {
+            return Err(Error::other(
+                "the image is too large for a GIF, reduce --cell-size",
+            ))
+        }
+    };
+    let mut encoder = gif::Encoder::new(writer, width, height, &palette()).map_err(Error::other)?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(Error::other)?;
+    for (raster, delay) in delays(&rasters, Duration::from_millis(10), MIN_GIF_DELAY) {
+        let mut frame = gif::Frame::from_indexed_pixels(width, height, &*raster.pixels, None);
+        frame.delay = delay.min(u16::MAX as u64) as u16;
+        encoder.write_frame(&frame).map_err(Error::other)?;
+    }
+    encoder.into_inner().map(|_| ())
+}
+
+pub(crate) fn write_apng<W: Write>(
+    timeline: &Timeline,
+    cell_size: u32,
+    writer: &mut W,
+) -> Result<()> {
+    let rasters = rasterize(timeline, cell_size);
+    let frames = delays(&rasters, Duration::from_millis(1), 1);
+    let mut encoder = png::Encoder::new(writer, rasters[0].width, rasters[0].height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(Cow::Owned(palette()));
+    // the frames are mostly flat colors, they compress well
+    encoder.set_compression(png::Compression::Best);
+    encoder
+        .set_animated(frames.len() as u32, 0)
+        .map_err(Error::other)?;
+    let mut png_writer = encoder.write_header().map_err(Error::other)?;
+    for (raster, delay) in frames {
+        png_writer
+            .set_frame_delay(delay.min(u16::MAX as u64) as u16, 1000)
+            .map_err(Error::other)?;
+        png_writer
+            .write_image_data(&raster.pixels)
+            .map_err(Error::other)?;
+    }
+    png_writer.finish().map_err(Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rasters(delays: &[u64]) -> Vec<Raster> {
+        delays
+            .iter()
+            .map(|delay| Raster {
+                width: 1,
+                height: 1,
+                pixels: vec![0],
+                delay: Duration::from_micros(*delay),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_keep_the_total_duration_when_frames_are_too_short() {
+        // 100ms frames at 16x
+        let rasters = rasters(&[6250; 30]);
+        let shown = delays(&rasters, Duration::from_millis(10), MIN_GIF_DELAY);
+        assert!(shown.iter().all(|(_, delay)| *delay >= MIN_GIF_DELAY));
+        assert_eq!(shown.iter().map(|(_, delay)| delay).sum::<u64>(), 19);
+        assert!(shown.len() < rasters.len());
+        assert!(std::ptr::eq(
+            shown.last().unwrap().0,
+            rasters.last().unwrap()
+        ));
+    }
+
+    #[test]
+    fn should_not_add_up_the_rounding_errors() {
+        let rasters = rasters(&[33_333; 90]);
+        let shown = delays(&rasters, Duration::from_millis(10), MIN_GIF_DELAY);
+        assert_eq!(shown.len(), 90);
+        assert_eq!(shown.iter().map(|(_, delay)| delay).sum::<u64>(), 300);
+    }
+
+    #[test]
+    fn should_always_show_the_last_frame() {
+        let last_too_short = rasters(&[50_000, 5_000]);
+        let shown = delays(&last_too_short, Duration::from_millis(10), MIN_GIF_DELAY);
+        assert_eq!(shown.len(), 2);
+        assert!(std::ptr::eq(shown[1].0, &last_too_short[1]));
+        assert_eq!((shown[0].1, shown[1].1), (4, MIN_GIF_DELAY));
+
+        // the one before can't give time away without getting too short itself
+        let both_short = rasters(&[25_000, 5_000]);
+        let shown = delays(&both_short, Duration::from_millis(10), MIN_GIF_DELAY);
+        assert_eq!(shown.len(), 1);
+        assert!(std::ptr::eq(shown[0].0, &both_short[1]));
+        assert_eq!(shown[0].1, 3);
+
+        let only_one = rasters(&[5_000]);
+        let shown = delays(&only_one, Duration::from_millis(10), MIN_GIF_DELAY);
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].1, MIN_GIF_DELAY);
+    }
+}
@@ -0,0 +1,171 @@
+//! asciicast v2 (`<https://docs.asciinema.org/manual/asciicast/v2/>`): a JSON header,
+//! then one `[time, "o", data]` event per frame, `data` being what `render` prints for it
+
+use std::io::{Result, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::json;
+
+use crate::export::Timeline;
+use crate::input::Grid;
+use crate::render::{prepare_grid, prepare_session, render_frame, RenderGrid};
+
+/// Lines printed by `render` below the board (score, help, blank line and version)
+const LINES_BELOW_BOARD: u32 = 5;
+const MIN_WIDTH: u32 = 80;
+
+/// Size of the terminal, large enough for the boards of all the sessions
+fn terminal_size(timeline: &Timeline) -> (u32, u32) {
+    timeline
+        .headers
+        .iter()
+        .map(|options| {
+            let columns = match options.grid {
+                Grid::Square => options.size.width,
+                Grid::Hex => options.size.width * 2,
+            };
+            // + the borders
+            (columns + 2, options.size.height + 2 + LINES_BELOW_BOARD)
+        })
+        .fold((MIN_WIDTH, 0), |(width, height), (columns, rows)| {
+            (width.max(columns), height.max(rows))
+        })
+}
+
+pub(crate) fn write<W: Write>(timeline: &Timeline, writer: &mut W) -> Result<()> {
+    let (width, height) = terminal_size(timeline);
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let header = json!({
+        "version": 2,
+        "width": width,
+        "height": height,
+        "timestamp": timestamp,
+        "env": { "TERM": "xterm-256color" },
+    });
+    writeln!(writer, "{}", header)?;
+    let mut session = None;
+    let (mut version, mut formatted_metadatas) = (String::new(), String::new());
+    for timed_game in &timeline.games {
+        let options = &timeline.headers[timed_game.session];
+        let mut data: Vec<u8> = Vec::new();
+        if session != Some(timed_game.session) {
+            // a new session clears the screen, the board may not have the same size
+            (version, formatted_metadatas) = prepare_session(options, &mut data);
+            session = Some(timed_game.session);
+        }
+        let mut grid = RenderGrid::new(options.size.width, options.size.height, options.grid);
+        prepare_grid(&mut grid, timed_game.game.clone());
+        render_frame(
+            &grid,
+            &version,
+            &formatted_metadatas,
+            options.size.width,
+            &timed_game.game,
+            &mut data,
+        );
+        let event = json!([
+            timed_game.at.as_secs_f64(),
+            "o",
+            String::from_utf8_lossy(&data)
+        ]);
+        writeln!(writer, "{}", event)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Frame;
+    use crate::throttle::Playback;
+
+    fn options(width: u32, height: u32) -> Frame {
+        Frame::parse(&format!(
+            r#"{{"frameDuration":100,"size":{{"width":{},"height":{}}},"featuresWithVersion":{{}},"metadatas":{{}}}}"#,
+            width, height
+        ))
+        .unwrap()
+    }
+
+    fn game(score: u32, elapsed: u64) -> Frame {
+        Frame::parse(&format!(
+            r#"{{"snake":{{"direction":"right","head":{{"x":1,"y":1}},"tail":[]}},"fruit":{{"x":3,"y":3}},"score":{},"state":"running","elapsed":{}}}"#,
+            score, elapsed
+        ))
+        .unwrap()
+    }
+
+    /// The header and the events of the asciicast
+    fn export(frames: Vec<Frame>) -> (serde_json::Value, Vec<serde_json::Value>) {
+        let mut frames = frames.into_iter();
+        let Some(Frame::Options(options)) = frames.next() else {
+            panic!("the frames start with a header")
+        };
+        let playback = Playback {
+            frame_duration: None,
+            max_pause: None,
+            speed: 1.0,
+            loop_infinite: false,
+        };
+        let timeline = Timeline::new(options, frames, playback, 0..=usize::MAX);
+        let mut cast = Vec::new();
+        write(&timeline, &mut cast).unwrap();
+        let mut lines = std::str::from_utf8(&cast)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap());
+        (lines.next().unwrap(), lines.collect())
+    }
+
+    #[test]
+    fn should_fit_the_boards_of_all_the_sessions_in_the_header() {
+        let (header, _) = export(vec![options(10, 10), game(0, 0)]);
+        assert_eq!(header["version"], 2);
+        assert_eq!(header["width"], MIN_WIDTH);
+        assert_eq!(header["height"], 10 + 2 + LINES_BELOW_BOARD);
+        assert!(header["timestamp"].as_u64().unwrap() > 0);
+
+        let (header, _) = export(vec![
+            options(10, 10),
+            game(0, 0),
+            options(100, 30),
+            game(0, 0),
+        ]);
+        assert_eq!(header["width"], 102);
+        assert_eq!(header["height"], 30 + 2 + LINES_BELOW_BOARD);
+    }
+
+    #[test]
+    fn should_output_each_frame_at_its_recorded_time() {
+        let (_, events) = export(vec![
+            options(10, 10),
+            game(0, 1000),
+            game(0, 1150),
+            game(10, 1400),
+            options(12, 12),
+            game(0, 0),
+        ]);
+        let times: Vec<f64> = events
+            .iter()
+            .map(|event| event[0].as_f64().unwrap())
+            .collect();
+        // the new session has no previous timing: the frame duration of its header
+        assert_eq!(times, [0.0, 0.15, 0.4, 0.5]);
+        for event in &events {
+            assert_eq!(event[1], "o");
+        }
+        let data: Vec<&str> = events
+            .iter()
+            .map(|event| event[2].as_str().unwrap())
+            .collect();
+        // the screen is only cleared when a session starts
+        let clear = "\u{1b}[2J";
+        assert!(data[0].starts_with(clear));
+        assert!(!data[1].contains(clear));
+        assert!(!data[2].contains(clear));
+        assert!(data[3].starts_with(clear));
+        assert!(data[2].contains("Score: 10"), "{}", data[2]);
+    }
+}
@@ -0,0 +1,163 @@
+//! One row per frame, for spreadsheets and data analysis
+//!
+//! The `event` column tells what happened since the previous frame of the session (several events are joined with `+`):
+//! `start` (first frame of a session), `turn`, `fruit` (eaten), `pause`, `resume`, `death` and `restart`.
+
+use std::io::{Result, Write};
+
+use serde::Serialize;
+
+use crate::export::Timeline;
+use crate::input::{Game, GameState};
+
+const HEADER: &str = "tick,head_x,head_y,direction,length,fruit_x,fruit_y,score,state,event";
+
+fn events(game: &Game, previous: Option<&Game>) -> Vec<&'static str> {
+    let previous = match previous {
+        Some(previous) => previous,
+        None => return vec!["start"],
+    };
+    let mut events = Vec::new();
+    if game.restarted_from(previous) {
+        events.push("restart");
+    } else {
+        if game.snake.direction != previous.snake.direction {
+            events.push("turn");
+        }
+        if game.score > previous.score {
+            events.push("fruit");
+        }
+    }
+    match (&previous.state, &game.state) {
+        (GameState::Paused, GameState::Paused) | (GameState::Over, GameState::Over) => {}
+        (_, GameState::Paused) => events.push("pause"),
+        (GameState::Paused, GameState::Running) => events.push("resume"),
+        (_, GameState::Over) => events.push("death"),
+        _ => {}
+    }
+    events
+}
+
+/// Same names as in the stream (`upleft`, `running` …)
+fn stream_name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+pub(crate) fn write<W: Write>(
+    timeline: &Timeline,
+    events_only: bool,
+    writer: &mut W,
+) -> Result<()> {
+    writeln!(writer, "{}", HEADER)?;
+    let mut previous: Option<(usize, &Game)> = None;
+    for timed_game in &timeline.games {
+        let game = &timed_game.game;
+        let previous_game = previous
+            .filter(|(session, _)| *session == timed_game.session)
+            .map(|(_, game)| game);
+        let events = events(game, previous_game);
+        previous = Some((timed_game.session, game));
+        if events_only && events.is_empty() {
+            continue;
+        }
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{},{}",
+            timed_game.tick,
+            game.snake.head.x,
+            game.snake.head.y,
+            stream_name(&game.snake.direction),
+            game.snake.tail.len() + 1,
+            game.fruit.x,
+            game.fruit.y,
+            game.score,
+            stream_name(&game.state),
+            events.join("+")
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::Timeline;
+    use crate::input::{Frame, InitOptions};
+    use crate::throttle::Playback;
+
+    const OPTIONS: &str = r#"{"frameDuration":100,"size":{"width":10,"height":10},"featuresWithVersion":{},"metadatas":{}}"#;
+
+    fn game(direction: &str, (x, y): (i32, i32), score: u32, state: &str) -> Game {
+        serde_json::from_str(&format!(
+            r#"{{"snake":{{"direction":"{}","head":{{"x":{},"y":{}}},"tail":[]}},"fruit":{{"x":9,"y":9}},"score":{},"state":"{}"}}"#,
+            direction, x, y, score, state
+        ))
+        .unwrap()
+    }
+
+    /// Eats a fruit while turning, dies, then restarts
+    fn games() -> Vec<Game> {
+        vec![
+            game("right", (1, 1), 0, "running"),
+            game("downright", (1, 2), 1, "running"),
+            game("downright", (1, 3), 1, "running"),
+            game("downright", (1, 3), 1, "over"),
+            game("right", (5, 5), 0, "running"),
+        ]
+    }
+
+    #[test]
+    fn should_tell_what_happened_since_the_previous_game() {
+        let games = games();
+        let events: Vec<Vec<&str>> = games
+            .iter()
+            .enumerate()
+            .map(|(index, game)| events(game, index.checked_sub(1).map(|index| &games[index])))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                vec!["start"],
+                vec!["turn", "fruit"],
+                vec![],
+                vec!["death"],
+                vec!["restart"],
+            ]
+        );
+    }
+
+    fn export(events_only: bool) -> String {
+        let options: InitOptions = serde_json::from_str(OPTIONS).unwrap();
+        let playback = Playback {
+            frame_duration: None,
+            max_pause: None,
+            speed: 1.0,
+            loop_infinite: false,
+        };
+        let frames = games().into_iter().map(Frame::Game);
+        let timeline = Timeline::new(options, frames, playback, 0..=usize::MAX);
+        let mut csv = Vec::new();
+        write(&timeline, events_only, &mut csv).unwrap();
+        String::from_utf8(csv).unwrap()
+    }
+
+    #[test]
+    fn should_only_keep_the_rows_with_events() {
+        assert_eq!(export(false).lines().count(), 6);
+        assert_eq!(
+            export(true),
+            [
+                HEADER,
+                "0,1,1,right,1,9,9,0,running,start",
+                "1,1,2,downright,1,9,9,1,running,turn+fruit",
+                "3,1,3,downright,1,9,9,1,over,death",
+                "4,5,5,right,1,9,9,0,running,restart",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
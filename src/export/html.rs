@@ -0,0 +1,152 @@
+//! A single HTML file replaying the recording offline, with the renderers of `render-browser`
+//!
+//! The renderers are ES modules: they are embedded as `data:` URLs, mapped to `snakepipe/renderers/…` by an import map
+//! (relative imports can't be resolved from a `data:` URL, they are rewritten to these names).
+
+use std::io::{Result, Write};
+
+use serde_json::json;
+
+use crate::export::Timeline;
+use crate::render_browser::generate;
+
+const TEMPLATE: &str = include_str!("replay.html");
+
+/// Prefix of the names of the embedded modules
+const MODULES: &str = "snakepipe/";
+
+/// Characters left as is in a `data:` URL, the others are percent-encoded
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte)
+}
+
+fn data_url(mime_type: &str, data: &str) -> String {
+    let mut url = format!("data:{};charset=utf-8,", mime_type);
+    for byte in data.bytes() {
+        if is_unreserved(byte) {
+            url.push(byte as char);
+        } else {
+            url.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    url
+}
+
+/// JSON that can be put inside a `<script>` (which would end at the first `</`)
+fn script_json(value: &serde_json::Value) -> String {
+    value.to_string().replace("</", "<\\/")
+}
+
+pub(crate) fn write<W: Write>(timeline: &Timeline, writer: &mut W) -> Result<()> {
+    let resources = generate();
+    let text = |name: &str| {
+        resources.get(name).map_or(String::new(), |resource| {
+            String::from_utf8_lossy(resource.data).into_owned()
+        })
+    };
+    let mut imports = serde_json::Map::new();
+    for name in resources
+        .keys()
+        .filter(|name| name.starts_with("renderers/") && name.ends_with(".js"))
+    {
+        let source = text(name)
+            .replace("from './", &format!("from '{}renderers/", MODULES))
+            .replace("from \"./", &format!("from \"{}renderers/", MODULES));
+        imports.insert(
+            format!("{}{}", MODULES, name),
+            data_url("text/javascript", &source).into(),
+        );
+    }
+    let recording = json!({
+        "headers": timeline.headers,
+        "games": timeline.games.iter().map(|timed_game| json!({
+            "session": timed_game.session,
+            "at": timed_game.at.as_millis() as u64,
+            "game": timed_game.game,
+        })).collect::<Vec<_>>(),
+        "duration": timeline.duration.as_millis() as u64,
+    });
+    let html = TEMPLATE
+        .replace("{{style}}", &text("main.css"))
+        .replace(
+            "{{importmap}}",
+            &script_json(&json!({ "imports": imports })),
+        )
+        .replace("{{recording}}", &script_json(&recording));
+    writer.write_all(html.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{Frame, InitOptions};
+    use crate::throttle::Playback;
+
+    const OPTIONS: &str = r#"{"frameDuration":100,"size":{"width":10,"height":10},"featuresWithVersion":{},"metadatas":{"name":"</script><script>alert(1)"}}"#;
+
+    fn game(score: u32, elapsed: u64) -> Frame {
+        Frame::parse(&format!(
+            r#"{{"snake":{{"direction":"right","head":{{"x":1,"y":1}},"tail":[]}},"fruit":{{"x":3,"y":3}},"score":{},"state":"running","elapsed":{}}}"#,
+            score, elapsed
+        ))
+        .unwrap()
+    }
+
+    fn export() -> String {
+        let options: InitOptions = serde_json::from_str(OPTIONS).unwrap();
+        let playback = Playback {
+            frame_duration: None,
+            max_pause: None,
+            speed: 1.0,
+            loop_infinite: false,
+        };
+        let frames = vec![game(0, 0), game(10, 250)].into_iter();
+        let timeline = Timeline::new(options, frames, playback, 0..=usize::MAX);
+        let mut html = Vec::new();
+        write(&timeline, &mut html).unwrap();
+        String::from_utf8(html).unwrap()
+    }
+
+    /// The content of the `<script>` that has `attributes`
+    fn script<'a>(html: &'a str, attributes: &str) -> &'a str {
+        let start = html.find(&format!("<script {}>", attributes)).unwrap();
+        let content = &html[start..];
+        let content = &content[content.find('>').unwrap() + 1..];
+        &content[..content.find("</script>").unwrap()]
+    }
+
+    #[test]
+    fn should_embed_the_frames() {
+        let html = export();
+        let recording: serde_json::Value =
+            serde_json::from_str(script(&html, r#"type="application/json" id="recording""#))
+                .unwrap();
+        assert_eq!(
+            recording["headers"][0]["metadatas"]["name"],
+            "</script><script>alert(1)"
+        );
+        let games = recording["games"].as_array().unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[1]["at"], 250);
+        assert_eq!(games[1]["session"], 0);
+        assert_eq!(games[1]["game"]["score"], 10);
+        assert_eq!(recording["duration"], 350);
+    }
+
+    #[test]
+    fn should_embed_the_renderers() {
+        let html = export();
+        assert!(!html.contains("{{style}}"));
+        let importmap: serde_json::Value =
+            serde_json::from_str(script(&html, r#"type="importmap""#)).unwrap();
+        let imports = importmap["imports"].as_object().unwrap();
+        assert!(!imports.is_empty());
+        for (name, url) in imports {
+            assert!(name.starts_with("snakepipe/renderers/"), "{}", name);
+            let url = url.as_str().unwrap();
+            assert!(url.starts_with("data:text/javascript;charset=utf-8,"));
+            // relative imports can't be resolved from a data: URL
+            assert!(!url.contains("from%20%27.%2F"), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Exports of a recording to other formats (`snakepipe export`)
+//!
+//! The whole recording is read first, the frames are timed the way `throttle` would replay them.
+
+mod animated;
+mod asciicast;
+mod csv;
+mod html;
+pub(crate) mod raster;
+
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::time::Duration;
+
+use clap::ValueEnum;
+
+use crate::input::{parse_gamestate_from, Frame, Game, InitOptions, ParseOptions};
+use crate::recording::input_name;
+use crate::throttle::Playback;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// asciinema recording (asciicast v2, `.cast`), drawn like `render` does
+    Asciicast,
+    /// Animated GIF, one square of `cell_size` pixels per cell
+    Gif,
+    /// Animated PNG, one square of `cell_size` pixels per cell
+    Apng,
+    /// Single HTML file replaying the recording with the renderers of `render-browser` (no server needed)
+    Html,
+    /// One row per frame: tick, head, direction, length, fruit, score, state and what happened
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// 2.0 plays twice as fast, 0.5 twice as slow
+    pub speed: f64,
+    /// First tick (index of the game, starting at 0) to export
+    pub from_tick: Option<usize>,
+    /// Last tick to export (included)
+    pub to_tick: Option<usize>,
+    /// in pixels (gif and apng)
+    pub cell_size: u32,
+    /// Only the frames where something happened (csv)
+    pub events_only: bool,
+}
+
+/// A game of the recording, along with when it shows up
+pub(crate) struct TimedGame {
+    /// index of the game in the recording (before trimming)
+    pub tick: usize,
+    /// index of the header of its session in [`Timeline::headers`]
+    pub session: usize,
+    /// since the first game
+    pub at: Duration,
+    pub game: Game,
+}
+
+pub(crate) struct Timeline {
+    pub headers: Vec<InitOptions>,
+    pub games: Vec<TimedGame>,
+    /// the last game stays on screen for the frame duration of its session
+    pub duration: Duration,
+}
+
+impl Timeline {
+    /// Only keeps the games in `ticks`, the first one kept showing up at 0
+    fn new(
+        options: InitOptions,
+        frames: impl Iterator<Item = Frame>,
+        playback: Playback,
+        ticks: RangeInclusive<usize>,
+    ) -> Self {
+        let mut timeline = Timeline {
+            headers: vec![options],
+            games: Vec::new(),
+            duration: Duration::ZERO,
+        };
+        let mut tick = 0;
+        let mut at = Duration::ZERO;
+        let mut previous_elapsed: Option<u64> = None;
+        for frame in frames {
+            match frame {
+                Frame::Options(options) => {
+                    timeline.headers.push(options);
+                    previous_elapsed = None;
+                }
+                Frame::Game(_) if tick > *ticks.end() => break,
+                Frame::Game(game) => {
+                    let session = timeline.headers.len() - 1;
+                    if !timeline.games.is_empty() {
+                        at += playback.delay(
+                            timeline.headers[session].frame_duration,
+                            previous_elapsed,
+                            game.elapsed,
+                        );
+                    }
+                    previous_elapsed = game.elapsed;
+                    if ticks.contains(&tick) {
+                        timeline.games.push(TimedGame {
+                            tick,
+                            session,
+                            at,
+                            game,
+                        });
+                    }
+                    tick += 1;
+                }
+            }
+        }
+        if let Some(last) = timeline.games.last() {
+            timeline.duration =
+                last.at + playback.delay(timeline.headers[last.session].frame_duration, None, None);
+        }
+        timeline
+    }
+}
+
+/// Reads the whole input and writes it to `output` in the format of `export_options`
+pub fn run(
+    input_path: Option<&Path>,
+    export_options: ExportOptions,
+    mut output: Box<dyn Write>,
+    parse_options: ParseOptions,
+) {
+    let input = match parse_gamestate_from(input_path, parse_options) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!(
+                "Error occurred while parsing {}: \"{}\"",
+                input_name(input_path),
+                e
+            );
+            std::process::exit(exitcode::DATAERR);
+        }
+    };
+    let playback = Playback {
+        frame_duration: None,
+        max_pause: None,
+        speed: export_options.speed,
+        loop_infinite: false,
+    };
+    let ticks =
+        export_options.from_tick.unwrap_or(0)..=export_options.to_tick.unwrap_or(usize::MAX);
+    let timeline = Timeline::new(input.options, input.lines, playback, ticks);
+    if timeline.games.is_empty() {
+        eprintln!("Nothing to export in {}", input_name(input_path));
+        std::process::exit(exitcode::DATAERR);
+    }
+    let result = match export_options.format {
+        ExportFormat::Asciicast => asciicast::write(&timeline, &mut output),
+        ExportFormat::Gif => animated::write_gif(&timeline, export_options.cell_size, &mut output),
+        ExportFormat::Apng => {
+            animated::write_apng(&timeline, export_options.cell_size, &mut output)
+        }
+        ExportFormat::Html => html::write(&timeline, &mut output),
+        ExportFormat::Csv => csv::write(&timeline, export_options.events_only, &mut output),
+    };
+    if let Err(e) = result.and_then(|_| output.flush()) {
+        eprintln!("Error occurred while writing: \"{}\"", e);
+        std::process::exit(exitcode::IOERR);
+    }
+}
@@ -0,0 +1,217 @@
+//! Frames drawn as images (for the animated formats): one square per cell, the score line below the board
+//!
+//! The pixels are indexes in [`PALETTE`], all the frames have the same size (the largest board of the recording).
+
+use std::time::Duration;
+
+use crate::export::Timeline;
+use crate::input::Grid;
+use crate::render::{prepare_grid, Point, RenderGrid};
+
+pub(crate) const BACKGROUND: u8 = 0;
+const EMPTY: u8 = 1;
+const HEAD: u8 = 2;
+const TAIL: u8 = 3;
+const RIVAL_HEAD: u8 = 4;
+const RIVAL_TAIL: u8 = 5;
+const FRUIT: u8 = 6;
+const PORTAL: u8 = 7;
+const POWER_UP: u8 = 8;
+const WALL: u8 = 9;
+pub(crate) const TEXT: u8 = 10;
+const GHOST_HEAD: u8 = 11;
+const GHOST_TAIL: u8 = 12;
+
+/// RGB colors, in the order of the indexes above (the ghost is a dimmed snake)
+pub(crate) const PALETTE: [[u8; 3]; 13] = [
+    [24, 24, 24],
+    [44, 44, 44],
+    [102, 187, 106],
+    [46, 125, 50],
+    [255, 167, 38],
+    [230, 81, 0],
+    [229, 57, 53],
+    [171, 71, 188],
+    [66, 165, 245],
+    [97, 97, 97],
+    [224, 224, 224],
+    [64, 100, 66],
+    [45, 70, 47],
+];
+
+/// Glyphs of the score line, 3x5 pixels (one row per byte, the 3 lowest bits from left to right)
+fn glyph(character: char) -> [u8; 5] {
+    match character {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        _ => [0; 5],
+    }
+}
+
+pub(crate) struct Raster {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    /// how long it stays on screen
+    pub delay: Duration,
+}
+
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32, color: u8) {
+        for py in y..(y + height).min(self.height) {
+            for px in x..(x + width).min(self.width) {
+                self.pixels[(py * self.width + px) as usize] = color;
+            }
+        }
+    }
+
+    /// Text in capitals, each pixel of the glyphs being a `scale` wide square (what doesn't fit is cut)
+    fn text(&mut self, x: u32, y: u32, text: &str, scale: u32) {
+        for (index, character) in text.to_uppercase().chars().enumerate() {
+            let left = x + index as u32 * 4 * scale;
+            for (row, bits) in glyph(character).iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) != 0 {
+                        let (px, py) = (left + column * scale, y + row as u32 * scale);
+                        self.fill(px, py, scale, scale, TEXT);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn color(point: &Point) -> u8 {
+    match point {
+        Point::Head => HEAD,
+        Point::Tail => TAIL,
+        Point::RivalHead => RIVAL_HEAD,
+        Point::RivalTail => RIVAL_TAIL,
+        Point::GhostHead => GHOST_HEAD,
+        Point::GhostTail => GHOST_TAIL,
+        Point::Fruit => FRUIT,
+        Point::Portal => PORTAL,
+        Point::PowerUp(_) => POWER_UP,
+        Point::Wall => WALL,
+        Point::Heat(_) | Point::Nothing => EMPTY,
+    }
+}
+
+/// Draws each game of the timeline, consecutive identical images being merged into one (longer) frame
+pub(crate) fn rasterize(timeline: &Timeline, cell_size: u32) -> Vec<Raster> {
+    // odd rows of a hex grid are shifted by half a cell
+    let board_width = |grid: Grid, columns: u32| match grid {
+        Grid::Square => columns * cell_size,
+        Grid::Hex => columns * cell_size + cell_size / 2,
+    };
+    let scale = (cell_size / 4).max(1);
+    let text_height = 7 * scale;
+    let (width, board_height) = timeline
+        .headers
+        .iter()
+        .map(|options| {
+            (
+                board_width(options.grid, options.size.width),
+                options.size.height * cell_size,
+            )
+        })
+        .fold((0, 0), |(width, height), (columns, rows)| {
+            (width.max(columns), height.max(rows))
+        });
+    let height = board_height + text_height;
+    // cells are separated by a line of background, unless too small
+    let gap = if cell_size >= 4 { 1 } else { 0 };
+
+    let mut rasters: Vec<Raster> = Vec::new();
+    for (index, timed_game) in timeline.games.iter().enumerate() {
+        let options = &timeline.headers[timed_game.session];
+        let mut grid = RenderGrid::new(options.size.width, options.size.height, options.grid);
+        prepare_grid(&mut grid, timed_game.game.clone());
+        let mut canvas = Canvas {
+            width,
+            height,
+            pixels: vec![BACKGROUND; (width * height) as usize],
+        };
+        for (y, row) in grid.data.rows_iter().enumerate() {
+            let shift = match grid.grid {
+                Grid::Hex if y % 2 == 1 => cell_size / 2,
+                _ => 0,
+            };
+            for (x, point) in row.enumerate() {
+                canvas.fill(
+                    shift + x as u32 * cell_size,
+                    y as u32 * cell_size,
+                    cell_size - gap,
+                    cell_size - gap,
+                    color(point),
+                );
+            }
+        }
+        canvas.text(
+            scale,
+            board_height + scale,
+            &format!(
+                "Score: {} - {}",
+                timed_game.game.score, timed_game.game.state
+            ),
+            scale,
+        );
+        let next_at = timeline
+            .games
+            .get(index + 1)
+            .map_or(timeline.duration, |next| next.at);
+        let delay = next_at.saturating_sub(timed_game.at);
+        match rasters.last_mut() {
+            Some(previous) if previous.pixels == canvas.pixels => previous.delay += delay,
+            _ => rasters.push(Raster {
+                width,
+                height,
+                pixels: canvas.pixels,
+                delay,
+            }),
+        }
+    }
+    rasters
+}
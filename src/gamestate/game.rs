@@ -1,17 +1,37 @@
 use crossterm::event::KeyModifiers;
+use rand::seq::SliceRandom;
 use rand::Rng;
-use serde::Serialize;
+use serde::{Serialize, Serializer};
+use std::time::Instant;
 
-use crate::gamestate::physics::{Direction, Position};
+use crate::gamestate::ghost::Ghost;
+use crate::gamestate::physics::{Board, Bounds, Direction, Grid, Portal, Position};
+use crate::gamestate::power_up::{Effect, PowerUp, PowerUpKind};
+use crate::gamestate::rival::{Personality, Rival};
+use crate::gamestate::rng::with_rng;
 use crate::gamestate::snake::Snake;
 
-fn calc_random_pos(width: u32, height: u32) -> Position {
-    let mut rng = rand::thread_rng();
+fn calc_random_pos(board: &Board, bounds: &Bounds) -> Position {
+    with_rng(|rng| {
+        board.from_offset(&Position {
+            x: rng.gen_range(bounds.x..bounds.x + bounds.width as i32),
+            y: rng.gen_range(bounds.y..bounds.y + bounds.height as i32),
+        })
+    })
+}
 
-    Position {
-        x: rng.gen_range(0..width as i32),
-        y: rng.gen_range(0..height as i32),
-    }
+/// A random cell inside `bounds` that is neither a portal nor `is_taken`, none when there is no such cell left
+fn calc_free_pos(
+    board: &Board,
+    bounds: &Bounds,
+    is_taken: impl Fn(&Position) -> bool,
+) -> Option<Position> {
+    let free: Vec<Position> = (bounds.y..bounds.y + bounds.height as i32)
+        .flat_map(|y| (bounds.x..bounds.x + bounds.width as i32).map(move |x| Position { x, y }))
+        .map(|pos| board.from_offset(&pos))
+        .filter(|pos| !board.is_portal(pos) && !is_taken(pos))
+        .collect();
+    with_rng(|rng| free.choose(rng).cloned())
 }
 
 #[derive(Debug, Serialize, PartialEq, Clone)]
@@ -27,8 +47,6 @@ pub struct Game {
     snake: Snake,
     fruit: Position,
     #[serde(skip)]
-    size: (u32, u32),
-    #[serde(skip)]
     frame_duration: f64,
     #[serde(skip)]
     waiting_time: f64,
@@ -36,22 +54,130 @@ pub struct Game {
     pub state: GameState,
     #[serde(skip)]
     initial_snake_length: u32,
+    /// only streamed in shrinking arena mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bounds: Option<Bounds>,
+    #[serde(skip)]
+    shrink_every: Option<u32>,
+    #[serde(skip)]
+    ticks_since_shrink: u32,
+    #[serde(flatten)]
+    board: Board,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    rivals: Vec<Rival>,
+    #[serde(skip)]
+    power_ups_enabled: bool,
+    #[serde(rename = "powerUp", skip_serializing_if = "Option::is_none")]
+    power_up: Option<PowerUp>,
+    /// active power-ups
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    effects: Vec<Effect>,
+    /// previous run to race against
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ghost: Option<Ghost>,
+    /// streamed as the ms elapsed since then, so that replays can reproduce the original timing
+    #[serde(rename = "elapsed", serialize_with = "serialize_elapsed")]
+    started: Instant,
+}
+
+fn serialize_elapsed<S: Serializer>(started: &Instant, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(started.elapsed().as_millis() as u64)
 }
 
 impl Game {
     pub fn new(width: u32, height: u32, frame_duration: f64, snake_length: u32) -> Self {
+        let bounds = Bounds::new(width, height);
+        let board = Board::new(width, height);
         Self {
-            snake: Snake::new(calc_random_pos(width, height), snake_length),
-            fruit: calc_random_pos(width, height),
-            size: (width, height),
+            snake: Snake::new(calc_random_pos(&board, &bounds), snake_length),
+            fruit: calc_random_pos(&board, &bounds),
             frame_duration,
             waiting_time: 0.0,
             score: 0,
             state: GameState::Paused,
             initial_snake_length: snake_length,
+            bounds: None,
+            shrink_every: None,
+            ticks_since_shrink: 0,
+            board,
+            rivals: Vec::new(),
+            power_ups_enabled: false,
+            power_up: None,
+            effects: Vec::new(),
+            ghost: None,
+            started: Instant::now(),
+        }
+    }
+
+    /// Battle mode: the playable area shrinks by one ring of cells every `every` ticks
+    pub fn enable_shrinking_arena(&mut self, every: u32) {
+        self.bounds = Some(Bounds::new(self.board.width, self.board.height));
+        self.shrink_every = Some(every);
+    }
+
+    /// Hexagonal cells: the snake can go in six directions, turning left/right relative to its heading
+    pub fn enable_hex_grid(&mut self) {
+        self.board.grid = Grid::Hex;
+        let bounds = self.playable_bounds();
+        self.snake = self.new_snake(calc_random_pos(&self.board, &bounds));
+        self.respawn_fruit(&bounds);
+    }
+
+    /// Adds portals from a map, they are expected to be inside the board (as column/row)
+    ///
+    /// The snake and the fruit move away from them if they spawned on one.
+    pub fn add_portals(&mut self, portals: Vec<Portal>) {
+        let portals: Vec<Portal> = portals
+            .iter()
+            .map(|Portal(a, b)| Portal(self.board.from_offset(a), self.board.from_offset(b)))
+            .collect();
+        self.board.portals.extend(portals);
+        if self.board.is_portal(self.snake.get_head_pos()) {
+            self.snake = self.spawn_snake(&self.playable_bounds());
+        }
+        if self.board.is_portal(&self.fruit) {
+            self.respawn_fruit(&self.playable_bounds());
         }
     }
 
+    /// Places `count` pairs of portals on free cells of the board
+    pub fn add_random_portals(&mut self, count: u32) {
+        let bounds = self.playable_bounds();
+        let is_free = |game: &Self, pos: &Position| {
+            !game.board.is_portal(pos) && !game.snake.contains(pos) && *pos != game.fruit
+        };
+        for _ in 0..count {
+            let a = calc_random_pos(&self.board, &bounds);
+            let b = calc_random_pos(&self.board, &bounds);
+            // give up on crowded boards instead of looping forever
+            if a != b && is_free(self, &a) && is_free(self, &b) {
+                self.board.portals.push(Portal(a, b));
+            }
+        }
+    }
+
+    /// Spawns `count` snakes controlled by the cpu, competing for the fruit (as many as there is room for)
+    pub fn add_rivals(&mut self, count: u32) {
+        for index in 0..count as usize {
+            match self.calc_snake_pos(&self.playable_bounds()) {
+                Some(head) => self
+                    .rivals
+                    .push(Rival::new(Personality::nth(index), self.new_snake(head))),
+                None => break,
+            }
+        }
+    }
+
+    /// Power-ups randomly show up on the board (slow-motion, ghost, magnet)
+    pub fn enable_power_ups(&mut self) {
+        self.power_ups_enabled = true;
+    }
+
+    /// Replays a previous run in lockstep, nothing collides with it
+    pub fn add_ghost(&mut self, ghost: Ghost) {
+        self.ghost = Some(ghost);
+    }
+
     pub fn start(&mut self) {
         self.state = GameState::Running;
     }
@@ -65,38 +191,241 @@ impl Game {
     }
 
     pub fn restart(&mut self) {
-        self.snake = Snake::new(
-            calc_random_pos(self.size.0, self.size.1),
-            self.initial_snake_length,
-        );
-        self.fruit = calc_random_pos(self.size.0, self.size.1);
+        let bounds = Bounds::new(self.board.width, self.board.height);
+        self.snake = self.spawn_snake(&bounds);
+        self.respawn_fruit(&bounds);
         self.score = 0;
         self.state = GameState::Running;
+        self.ticks_since_shrink = 0;
+        self.power_up = None;
+        self.effects.clear();
+        if self.bounds.is_some() {
+            self.bounds = Some(bounds);
+        }
+        for rival in std::mem::take(&mut self.rivals) {
+            match self.calc_snake_pos(&bounds) {
+                Some(head) => self
+                    .rivals
+                    .push(Rival::new(rival.personality, self.new_snake(head))),
+                // no room left for the others
+                None => break,
+            }
+        }
+    }
+
+    /// Restarts on the board of `game` (set up from scratch), the elapsed time keeps counting for the recording
+    pub fn restart_as(&mut self, game: Game) {
+        *self = Self {
+            started: self.started,
+            state: GameState::Running,
+            ..game
+        };
+    }
+
+    /// On a hex grid, there is no straight down: the snake starts heading down right
+    fn new_snake(&self, head: Position) -> Snake {
+        let mut snake = Snake::new(head, self.initial_snake_length);
+        if self.board.grid == Grid::Hex {
+            snake.steer(Direction::DownRight);
+        }
+        snake
+    }
+
+    /// A new snake on a cell that is not a portal (anywhere if the board is nothing but portals)
+    fn spawn_snake(&self, bounds: &Bounds) -> Snake {
+        let head = calc_free_pos(&self.board, bounds, |_| false)
+            .unwrap_or_else(|| calc_random_pos(&self.board, bounds));
+        self.new_snake(head)
+    }
+
+    /// Bounds are expressed in columns/rows, so they need the offset coordinates on a hex grid
+    fn is_inside(&self, bounds: &Bounds, pos: &Position) -> bool {
+        bounds.contains(&self.board.to_offset(pos))
+    }
+
+    /// A free cell that no snake is on
+    fn calc_snake_pos(&self, bounds: &Bounds) -> Option<Position> {
+        calc_free_pos(&self.board, bounds, |pos| self.is_on_a_snake(pos))
+    }
+
+    /// Never on a portal (the fruit would be unreachable), it stays where it is if every cell is one
+    fn respawn_fruit(&mut self, bounds: &Bounds) {
+        if let Some(fruit) = calc_free_pos(&self.board, bounds, |_| false) {
+            self.fruit = fruit;
+        }
+    }
+
+    fn is_on_a_snake(&self, pos: &Position) -> bool {
+        self.snake.contains(pos) || self.rivals.iter().any(|rival| rival.snake.contains(pos))
+    }
+
+    fn has_effect(&self, kind: PowerUpKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    /// Wears off the active effects, picks up, spawns or makes the power-ups vanish
+    fn update_power_ups(&mut self, bounds: &Bounds) {
+        self.effects.retain_mut(|effect| {
+            effect.remaining -= 1;
+            effect.remaining > 0
+        });
+        if let Some(power_up) = self.power_up.as_mut() {
+            if power_up.position == *self.snake.get_head_pos() {
+                let kind = power_up.kind;
+                self.effects.retain(|effect| effect.kind != kind);
+                self.effects.push(Effect {
+                    kind,
+                    remaining: kind.duration(),
+                });
+                self.power_up = None;
+            } else if !power_up.tick()
+                || !bounds.contains(&self.board.to_offset(&power_up.position))
+            {
+                self.power_up = None;
+            }
+        } else if self.power_ups_enabled {
+            self.power_up = PowerUp::maybe_spawn(|| {
+                calc_free_pos(&self.board, bounds, |pos| {
+                    *pos == self.fruit || self.is_on_a_snake(pos)
+                })
+            });
+        }
+    }
+
+    /// Magnet effect: the fruit moves one cell towards the head of the snake
+    fn attract_fruit(&mut self, bounds: &Bounds) {
+        if !self.has_effect(PowerUpKind::Magnet) {
+            return;
+        }
+        let head = self.snake.get_head_pos();
+        let (dx, dy) = (head.x - self.fruit.x, head.y - self.fruit.y);
+        let mut next = self.fruit.clone();
+        if dx.abs() >= dy.abs() {
+            next.x += dx.signum();
+        } else {
+            next.y += dy.signum();
+        }
+        if self.is_inside(bounds, &next)
+            && !self.board.is_portal(&next)
+            && (next == *head || !self.is_on_a_snake(&next))
+        {
+            self.fruit = next;
+        }
+    }
+
+    /// Each cpu snake picks its next move according to its personality
+    fn steer_rivals(&mut self, bounds: &Bounds) {
+        let directions: Vec<Direction> = self
+            .rivals
+            .iter()
+            .map(|rival| {
+                rival.choose_direction(&self.board, &self.fruit, |pos| {
+                    !self.is_inside(bounds, pos) || self.is_on_a_snake(pos)
+                })
+            })
+            .collect();
+        for (rival, direction) in self.rivals.iter_mut().zip(directions) {
+            rival.snake.steer(direction);
+        }
+    }
+
+    /// Moves the cpu snakes - the ones that crash respawn somewhere else (or are gone if there is no room left)
+    fn update_rivals(&mut self, bounds: &Bounds) {
+        let mut index = 0;
+        while index < self.rivals.len() {
+            let next = self.rivals[index].snake.next_head_pos(&self.board);
+            if !self.is_inside(bounds, &next) || self.is_on_a_snake(&next) {
+                match self.calc_snake_pos(bounds) {
+                    Some(head) => {
+                        self.rivals[index] =
+                            Rival::new(self.rivals[index].personality, self.new_snake(head))
+                    }
+                    None => {
+                        self.rivals.remove(index);
+                        continue;
+                    }
+                }
+            } else if *self.rivals[index].snake.get_head_pos() == self.fruit {
+                self.rivals[index].snake.grow();
+                self.rivals[index].snake.update(&self.board);
+                self.respawn_fruit(bounds);
+            } else {
+                self.rivals[index].snake.update(&self.board);
+            }
+            index += 1;
+        }
+    }
+
+    /// The whole board, unless the arena has been shrinking
+    fn playable_bounds(&self) -> Bounds {
+        self.bounds
+            .unwrap_or_else(|| Bounds::new(self.board.width, self.board.height))
+    }
+
+    /// Turns the outer ring into walls each `shrink_every` ticks, respawns the fruit if it got walled
+    fn shrink_arena(&mut self) {
+        if let (Some(bounds), Some(every)) = (self.bounds.as_mut(), self.shrink_every) {
+            self.ticks_since_shrink += 1;
+            if self.ticks_since_shrink >= every {
+                self.ticks_since_shrink = 0;
+                if bounds.shrink() && !bounds.contains(&self.board.to_offset(&self.fruit)) {
+                    let bounds = *bounds;
+                    self.respawn_fruit(&bounds);
+                }
+            }
+        }
     }
 
     /// returns true if the state has been updated because it was time to
     pub fn update(&mut self, delta_time: f64) -> bool {
         self.waiting_time += delta_time;
 
-        if self.waiting_time > self.frame_duration && self.state != GameState::Over {
+        let frame_duration = if self.has_effect(PowerUpKind::SlowMotion) {
+            self.frame_duration * 2.0
+        } else {
+            self.frame_duration
+        };
+
+        if self.waiting_time > frame_duration && self.state != GameState::Over {
             self.waiting_time = 0.0;
 
             if self.state == GameState::Paused || self.state == GameState::Over {
                 return true;
             }
 
-            if !self.snake.is_tail_overlapping() && !self.snake.will_tail_overlap() {
+            self.shrink_arena();
+            let bounds = self.playable_bounds();
+            self.steer_rivals(&bounds);
+            let next_head_pos = self.snake.next_head_pos(&self.board);
+
+            let ghost = self.has_effect(PowerUpKind::Ghost);
+
+            if (ghost
+                || !self.snake.is_tail_overlapping() && !self.snake.will_tail_overlap(&self.board))
+                && self.is_inside(&bounds, self.snake.get_head_pos())
+                && self.is_inside(&bounds, &next_head_pos)
+                && !self
+                    .rivals
+                    .iter()
+                    .any(|rival| rival.snake.contains(&next_head_pos))
+            {
                 if *self.snake.get_head_pos() == self.fruit {
                     self.snake.grow();
-                    self.snake.update(self.size.0, self.size.1);
-                    self.fruit = calc_random_pos(self.size.0, self.size.1);
+                    self.snake.update(&self.board);
+                    self.respawn_fruit(&bounds);
                     self.calc_score();
                 } else {
-                    self.snake.update(self.size.0, self.size.1);
+                    self.snake.update(&self.board);
                 }
+                self.update_rivals(&bounds);
+                self.update_power_ups(&bounds);
+                self.attract_fruit(&bounds);
             } else {
                 self.state = GameState::Over;
             }
+            if let Some(ghost) = self.ghost.as_mut() {
+                ghost.tick();
+            }
             return true;
         }
         return false;
@@ -124,6 +453,25 @@ impl Game {
                 self.restart();
                 Some(())
             }
+            // on a hex grid, left/right turn relative to the heading of the snake
+            Event::Key(KeyEvent {
+                code: KeyCode::Left,
+                ..
+            }) if self.board.grid == Grid::Hex => {
+                self.snake.set_dir(self.snake.get_direction().turn_left());
+                Some(())
+            }
+            Event::Key(KeyEvent {
+                code: KeyCode::Right,
+                ..
+            }) if self.board.grid == Grid::Hex => {
+                self.snake.set_dir(self.snake.get_direction().turn_right());
+                Some(())
+            }
+            Event::Key(KeyEvent {
+                code: KeyCode::Up | KeyCode::Down,
+                ..
+            }) if self.board.grid == Grid::Hex => Some(()),
             Event::Key(KeyEvent {
                 code: KeyCode::Left,
                 ..
@@ -168,3 +516,165 @@ impl Game {
         self.score = (self.snake.get_len() * 10) as u32
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(x: i32, y: i32) -> Position {
+        Position { x, y }
+    }
+
+    /// Running game on a 10x10 board, the snake (of length 2) at `head`, heading towards `direction`
+    fn game(head: Position, direction: Direction) -> Game {
+        let mut game = Game::new(10, 10, 100.0, 2);
+        game.snake = Snake::new(head, 2);
+        game.snake.steer(direction);
+        game.fruit = pos(5, 8);
+        game.start();
+        game
+    }
+
+    /// Power-up of a random kind, lying on `position`
+    fn power_up(position: Position) -> PowerUp {
+        std::iter::repeat_with(|| PowerUp::maybe_spawn(|| Some(position.clone())))
+            .find_map(|power_up| power_up)
+            .unwrap()
+    }
+
+    /// Moves the game forward by one tick
+    fn tick(game: &mut Game) {
+        assert!(game.update(game.frame_duration * 2.0 + 1.0));
+    }
+
+    #[test]
+    fn should_move_the_snake_and_the_fruit_off_the_map_portals() {
+        for _ in 0..20 {
+            let mut game = game(pos(5, 5), Direction::Down);
+            game.fruit = pos(2, 2);
+            game.add_portals(vec![
+                Portal(pos(5, 5), pos(7, 7)),
+                Portal(pos(2, 2), pos(1, 8)),
+            ]);
+            assert!(!game.board.is_portal(game.snake.get_head_pos()));
+            assert!(!game.board.is_portal(&game.fruit));
+
+            game.restart();
+            assert!(!game.board.is_portal(game.snake.get_head_pos()));
+            assert!(!game.board.is_portal(&game.fruit));
+        }
+    }
+
+    #[test]
+    fn should_find_the_last_free_cells() {
+        let mut board = Board::new(5, 5);
+        board.portals = vec![Portal(pos(0, 0), pos(4, 4))];
+        let bounds = Bounds::new(5, 5);
+        let free = pos(3, 4);
+        assert_eq!(
+            calc_free_pos(&board, &bounds, |cell| *cell != free),
+            Some(free.clone())
+        );
+        // the portals are never free
+        assert_eq!(
+            calc_free_pos(&board, &bounds, |cell| *cell != free && *cell != pos(0, 0)),
+            Some(free.clone())
+        );
+        assert_eq!(calc_free_pos(&board, &bounds, |_| true), None);
+        // nor the cells outside the bounds
+        let bounds = Bounds {
+            x: 1,
+            y: 1,
+            width: 3,
+            height: 3,
+        };
+        assert_eq!(calc_free_pos(&board, &bounds, |cell| *cell != free), None);
+    }
+
+    #[test]
+    fn should_wear_off_the_effects() {
+        let mut game = game(pos(5, 5), Direction::Right);
+        game.effects = vec![
+            Effect {
+                kind: PowerUpKind::Ghost,
+                remaining: 1,
+            },
+            Effect {
+                kind: PowerUpKind::Magnet,
+                remaining: 2,
+            },
+        ];
+        tick(&mut game);
+        assert_eq!(game.effects.len(), 1);
+        assert_eq!(game.effects[0].kind, PowerUpKind::Magnet);
+        assert_eq!(game.effects[0].remaining, 1);
+        tick(&mut game);
+        assert!(game.effects.is_empty());
+    }
+
+    #[test]
+    fn should_pick_up_the_power_ups_for_their_duration() {
+        let mut game = game(pos(5, 5), Direction::Down);
+        game.fruit = pos(1, 1);
+        let power_up = power_up(pos(5, 6));
+        let kind = power_up.kind;
+        game.power_up = Some(power_up);
+        tick(&mut game);
+        assert!(game.power_up.is_none());
+        assert_eq!(game.effects.len(), 1);
+        assert_eq!(game.effects[0].kind, kind);
+        assert_eq!(game.effects[0].remaining, kind.duration());
+
+        for _ in 1..kind.duration() {
+            tick(&mut game);
+            assert!(game.has_effect(kind));
+        }
+        tick(&mut game);
+        assert!(!game.has_effect(kind));
+        assert_eq!(game.state, GameState::Running);
+    }
+
+    #[test]
+    fn should_drop_the_power_ups_left_outside_of_the_arena() {
+        let mut game = game(pos(5, 5), Direction::Right);
+        game.enable_shrinking_arena(1);
+        game.power_up = Some(power_up(pos(0, 3)));
+        tick(&mut game);
+        assert!(game.power_up.is_none());
+        assert!(game.effects.is_empty());
+    }
+
+    #[test]
+    fn should_respawn_the_fruit_left_outside_of_the_arena() {
+        let mut game = game(pos(5, 5), Direction::Right);
+        game.enable_shrinking_arena(2);
+        game.fruit = pos(0, 0);
+        tick(&mut game);
+        assert_eq!(game.bounds, Some(Bounds::new(10, 10)));
+        assert_eq!(game.fruit, pos(0, 0));
+
+        tick(&mut game);
+        let bounds = game.bounds.unwrap();
+        assert_eq!(bounds.x, 1);
+        assert!(bounds.contains(&game.fruit));
+        assert_eq!(game.state, GameState::Running);
+    }
+
+    #[test]
+    fn should_end_the_game_when_the_snake_is_left_outside_of_the_arena() {
+        let mut game = game(pos(0, 5), Direction::Down);
+        game.enable_shrinking_arena(1);
+        tick(&mut game);
+        assert_eq!(game.state, GameState::Over);
+        // the snake didn't move
+        assert_eq!(*game.snake.get_head_pos(), pos(0, 5));
+    }
+
+    #[test]
+    fn should_end_the_game_when_the_snake_heads_into_the_new_wall() {
+        let mut game = game(pos(1, 5), Direction::Left);
+        game.enable_shrinking_arena(1);
+        tick(&mut game);
+        assert_eq!(game.state, GameState::Over);
+    }
+}
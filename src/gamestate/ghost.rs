@@ -0,0 +1,141 @@
+//! A previous run replayed in lockstep with the game (`gamestate --ghost`), to race against it
+//!
+//! The ghost moves one recorded tick each time the snake moves, nothing collides with it.
+//! Once its run is over, it stays where it died.
+
+use std::path::Path;
+
+use serde::{Serialize, Serializer};
+
+use crate::input::{
+    parse_gamestate_from, Frame, Game, GameState, InitOptions, ParseOptions, Snake,
+};
+
+#[derive(Debug, Clone)]
+pub struct Ghost {
+    /// the snake at each tick of the run
+    snakes: Vec<Snake>,
+    /// number of ticks played
+    tick: usize,
+}
+
+impl Ghost {
+    /// Loads the first run of a recording, along with its header (for the board and the seed)
+    ///
+    /// The run ends at the first game over (or restart), the ticks where the game was paused are skipped.
+    pub fn from_recording(
+        path: &Path,
+        parse_options: ParseOptions,
+    ) -> Result<(InitOptions, Self), Box<dyn std::error::Error>> {
+        let input = parse_gamestate_from(Some(path), parse_options)?;
+        let ghost = Self::from_frames(input.lines).ok_or("no game was played")?;
+        Ok((input.options, ghost))
+    }
+
+    /// The first run of the session, none if the snake never moved
+    fn from_frames(frames: impl Iterator<Item = Frame>) -> Option<Self> {
+        let mut snakes = Vec::new();
+        let mut previous: Option<Game> = None;
+        for frame in frames {
+            let game = match frame {
+                // a new session, on another board
+                Frame::Options(_) => break,
+                Frame::Game(game) => game,
+            };
+            if previous
+                .as_ref()
+                .is_some_and(|previous| game.restarted_from(previous))
+            {
+                break;
+            }
+            // the snake doesn't move while paused nor once over
+            if game.state == GameState::Running {
+                snakes.push(game.snake.clone());
+            }
+            previous = Some(game);
+        }
+        (!snakes.is_empty()).then_some(Self { snakes, tick: 0 })
+    }
+
+    /// The same run, from its start
+    pub fn rewound(&self) -> Self {
+        Self {
+            snakes: self.snakes.clone(),
+            tick: 0,
+        }
+    }
+
+    /// Moves to the next tick of the run
+    pub fn tick(&mut self) {
+        self.tick += 1;
+    }
+
+    pub fn snake(&self) -> &Snake {
+        &self.snakes[self.tick.saturating_sub(1).min(self.snakes.len() - 1)]
+    }
+}
+
+/// Streamed as its snake at the current tick
+impl Serialize for Ghost {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.snake().serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(x: i32, score: u32, state: &str) -> Frame {
+        Frame::Game(
+            serde_json::from_str(&format!(
+                r#"{{"snake":{{"direction":"right","head":{{"x":{},"y":0}},"tail":[]}},"fruit":{{"x":9,"y":9}},"score":{},"state":"{}"}}"#,
+                x, score, state
+            ))
+            .unwrap(),
+        )
+    }
+
+    fn heads(ghost: &mut Ghost) -> Vec<i32> {
+        (0..ghost.snakes.len() + 1)
+            .map(|_| {
+                ghost.tick();
+                ghost.snake().head.x
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_end_the_run_at_the_first_restart() {
+        let frames = vec![
+            game(0, 0, "running"),
+            game(1, 0, "paused"),
+            game(1, 0, "running"),
+            game(2, 1, "running"),
+            game(3, 1, "over"),
+            game(3, 1, "over"),
+            // restarted
+            game(7, 0, "running"),
+            game(8, 0, "running"),
+        ];
+        let mut ghost = Ghost::from_frames(frames.into_iter()).unwrap();
+        // stays where it died
+        assert_eq!(heads(&mut ghost), [0, 1, 2, 2]);
+    }
+
+    #[test]
+    fn should_end_the_run_when_restarted_while_running() {
+        let frames = vec![
+            game(0, 0, "running"),
+            game(1, 2, "running"),
+            game(7, 0, "running"),
+        ];
+        let mut ghost = Ghost::from_frames(frames.into_iter()).unwrap();
+        assert_eq!(heads(&mut ghost), [0, 1, 1]);
+    }
+
+    #[test]
+    fn should_need_a_game_played() {
+        assert!(Ghost::from_frames(vec![game(0, 0, "paused")].into_iter()).is_none());
+    }
+}
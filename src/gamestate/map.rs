@@ -0,0 +1,58 @@
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::input::{Portal, Position};
+
+/// Layout of the board loaded from a json file with `--map`
+///
+/// Example:
+/// ```json
+/// {
+///   "portals": [
+///     [{ "x": 2, "y": 2 }, { "x": 20, "y": 18 }]
+///   ]
+/// }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct Map {
+    #[serde(default)]
+    pub portals: Vec<Portal>,
+}
+
+impl Map {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Makes sure the map fits in a board of `width` x `height`, each cell being the end of one portal at most
+    pub fn check(&self, width: u32, height: u32) -> Result<(), String> {
+        let mut ends: Vec<&Position> = Vec::new();
+        for Portal(a, b) in self.portals.iter() {
+            for pos in [a, b] {
+                if pos.x < 0 || pos.y < 0 || pos.x >= width as i32 || pos.y >= height as i32 {
+                    return Err(format!(
+                        "portal at x: {} / y: {} is outside of the {}x{} board",
+                        pos.x, pos.y, width, height
+                    ));
+                }
+            }
+            if a == b {
+                return Err(format!(
+                    "both ends of the portal are at x: {} / y: {}",
+                    a.x, a.y
+                ));
+            }
+            for pos in [a, b] {
+                if ends.contains(&pos) {
+                    return Err(format!(
+                        "several portals end at x: {} / y: {}",
+                        pos.x, pos.y
+                    ));
+                }
+                ends.push(pos);
+            }
+        }
+        Ok(())
+    }
+}
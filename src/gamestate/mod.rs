@@ -1,29 +1,80 @@
 pub mod game;
+pub mod ghost;
+pub mod map;
 pub mod physics;
+pub mod power_up;
+pub mod rival;
+pub mod rng;
 pub mod snake;
 
 use std::io::Write;
 use std::time::{Duration, Instant};
 
-use crossterm::event::{poll, read};
+use crossterm::event::{poll, read, Event, KeyCode, KeyEvent};
 
 use crate::gamestate::game::GameState;
-use crate::input::InitOptions;
+use crate::gamestate::ghost::Ghost;
+use crate::input::{Grid, InitOptions, Portal, Position};
+
+fn position(position: &Position) -> physics::Position {
+    physics::Position {
+        x: position.x,
+        y: position.y,
+    }
+}
+
+/// Sets up a game with the `options` (the same seed giving the same board)
+fn new_game(options: &InitOptions, ghost: Option<&Ghost>) -> game::Game {
+    if let Some(seed) = options.seed {
+        rng::seed(seed);
+    }
+    let mut game = game::Game::new(
+        options.size.width,
+        options.size.height,
+        options.frame_duration as f64,
+        options.snake_length,
+    );
+    if options.grid == Grid::Hex {
+        game.enable_hex_grid();
+    }
+    if let Some(shrink_every) = options.shrink_every {
+        game.enable_shrinking_arena(shrink_every);
+    }
+    game.add_portals(
+        options
+            .map_portals
+            .iter()
+            .map(|Portal(a, b)| physics::Portal(position(a), position(b)))
+            .collect(),
+    );
+    game.add_random_portals(options.portals);
+    game.add_rivals(options.rivals);
+    if options.power_ups {
+        game.enable_power_ups();
+    }
+    if let Some(ghost) = ghost {
+        game.add_ghost(ghost.rewound());
+    }
+    game
+}
 
 /**
  * This function is the update loop.
  * It keeps track of the user inputs via the keyboard.
  * It runs forever and returns if ctrl+c is hit.
+ * What is output on stdout is also written to `recording` (complete once dropped, when returning).
  */
-pub fn run(options: InitOptions) -> std::io::Result<()> {
+pub fn run(
+    options: InitOptions,
+    ghost: Option<Ghost>,
+    mut recording: Option<Box<dyn Write>>,
+) -> std::io::Result<()> {
     let mut stdout = std::io::stdout();
     stdout.write(format!("{}\r\n", serde_json::to_string(&options).unwrap()).as_bytes())?;
-    let mut main = game::Game::new(
-        options.size.width,
-        options.size.height,
-        options.frame_duration as f64,
-        options.snake_length,
-    );
+    if let Some(file) = recording.as_mut() {
+        writeln!(file, "{}", serde_json::to_string(&options).unwrap())?;
+    }
+    let mut main = new_game(&options, ghost.as_ref());
     let mut last_loop_duration: Duration = Duration::new(0, 0);
     main.start();
     let mut prev_state = main.state.clone();
@@ -32,8 +83,18 @@ pub fn run(options: InitOptions) -> std::io::Result<()> {
         if poll(Duration::from_millis(20))? {
             let event = read()?;
 
-            // return Ok(()) when ctrl+c is hit
-            if let None = main.key_down(event) {
+            let restart = matches!(
+                event,
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char('r'),
+                    ..
+                })
+            );
+            if restart && ghost.is_some() {
+                // racing a ghost: restarting is on the same board, the ghost starting over too
+                main.restart_as(new_game(&options, ghost.as_ref()));
+            } else if let None = main.key_down(event) {
+                // return Ok(()) when ctrl+c is hit
                 return Ok(());
             }
         }
@@ -44,9 +105,49 @@ pub fn run(options: InitOptions) -> std::io::Result<()> {
             {
                 stdout
                     .write(format!("{}\r\n", serde_json::to_string(&main).unwrap()).as_bytes())?;
+                if let Some(file) = recording.as_mut() {
+                    writeln!(file, "{}", serde_json::to_string(&main).unwrap())?;
+                }
             }
             prev_state = main.state.clone();
         }
         last_loop_duration = start.elapsed();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(seed: u64) -> InitOptions {
+        let mut options: InitOptions = serde_json::from_str(
+            r#"{"frameDuration":120,"size":{"width":25,"height":25},"snakeLength":2,"portals":3,"mapPortals":[[{"x":1,"y":1},{"x":20,"y":20}]],"rivals":2,"powerUps":true,"featuresWithVersion":{},"metadatas":{}}"#,
+        )
+        .unwrap();
+        options.seed = Some(seed);
+        options
+    }
+
+    /// Where the game starts: the snakes, the fruit and the portals
+    fn board(options: &InitOptions) -> serde_json::Value {
+        let mut game = serde_json::to_value(new_game(options, None)).unwrap();
+        game.as_object_mut().unwrap().remove("elapsed");
+        game
+    }
+
+    #[test]
+    fn should_set_up_the_same_board_from_the_same_seed_and_options() {
+        let board = board(&options(42));
+        assert_eq!(board["portals"].as_array().unwrap().len(), 4);
+        assert_eq!(board, self::board(&options(42)));
+        assert_ne!(board, self::board(&options(43)));
+    }
+
+    #[test]
+    fn should_read_back_the_options_from_the_header() {
+        let options = options(42);
+        let header = serde_json::to_string(&options).unwrap();
+        let read: InitOptions = serde_json::from_str(&header).unwrap();
+        assert_eq!(board(&read), board(&options));
+    }
+}
@@ -1,18 +1,32 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The playable area can't shrink below this size (in cells, on both axis)
+const MIN_BOUNDS_SIZE: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
 }
 
 impl Position {
+    /// On a hex grid, positions are axial coordinates (x is q, y is r)
     pub fn move_to_dir(&mut self, dir: Direction) {
         match dir {
             Direction::Up => self.y -= 1,
             Direction::Down => self.y += 1,
             Direction::Left => self.x -= 1,
             Direction::Right => self.x += 1,
+            Direction::UpLeft => self.y -= 1,
+            Direction::UpRight => {
+                self.x += 1;
+                self.y -= 1;
+            }
+            Direction::DownLeft => {
+                self.x -= 1;
+                self.y += 1;
+            }
+            Direction::DownRight => self.y += 1,
         }
     }
 }
@@ -24,8 +38,30 @@ pub enum Direction {
     Right,
     Down,
     Left,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
 }
 
+/// Directions available on a square grid
+pub const SQUARE_DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+/// Directions available on a hex grid, counterclockwise
+pub const HEX_DIRECTIONS: [Direction; 6] = [
+    Direction::Right,
+    Direction::UpRight,
+    Direction::UpLeft,
+    Direction::Left,
+    Direction::DownLeft,
+    Direction::DownRight,
+];
+
 impl Direction {
     pub fn opposite(&self) -> Direction {
         match *self {
@@ -33,6 +69,318 @@ impl Direction {
             Direction::Right => Direction::Left,
             Direction::Down => Direction::Up,
             Direction::Left => Direction::Right,
+            Direction::UpLeft => Direction::DownRight,
+            Direction::UpRight => Direction::DownLeft,
+            Direction::DownLeft => Direction::UpRight,
+            Direction::DownRight => Direction::UpLeft,
+        }
+    }
+
+    /// Next direction counterclockwise on a hex grid
+    pub fn turn_left(&self) -> Direction {
+        self.turn(1)
+    }
+
+    /// Next direction clockwise on a hex grid
+    pub fn turn_right(&self) -> Direction {
+        self.turn(HEX_DIRECTIONS.len() - 1)
+    }
+
+    fn turn(&self, steps: usize) -> Direction {
+        match HEX_DIRECTIONS.iter().position(|dir| dir == self) {
+            Some(index) => HEX_DIRECTIONS[(index + steps) % HEX_DIRECTIONS.len()],
+            // square directions are not part of the hex set, they keep going straight
+            None => *self,
+        }
+    }
+}
+
+/// Layout of the cells of the board
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Grid {
+    #[default]
+    Square,
+    /// Rows of hexagons, odd rows shifted by half a cell ("odd-r" layout)
+    Hex,
+}
+
+/// Playable area of the board - the cells outside of it are walls
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct Bounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Bounds {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    pub fn contains(&self, pos: &Position) -> bool {
+        pos.x >= self.x
+            && pos.y >= self.y
+            && pos.x < self.x + self.width as i32
+            && pos.y < self.y + self.height as i32
+    }
+
+    /// Turns the outer ring of cells into walls, returns false if the area is already too small
+    pub fn shrink(&mut self) -> bool {
+        if self.width < MIN_BOUNDS_SIZE + 2 || self.height < MIN_BOUNDS_SIZE + 2 {
+            return false;
+        }
+        self.x += 1;
+        self.y += 1;
+        self.width -= 2;
+        self.height -= 2;
+        true
+    }
+}
+
+/// Pair of cells teleporting the head of the snake from one to the other
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Portal(pub Position, pub Position);
+
+impl Portal {
+    /// If `pos` is one end of the portal, returns the other one
+    pub fn twin(&self, pos: &Position) -> Option<&Position> {
+        if *pos == self.0 {
+            Some(&self.1)
+        } else if *pos == self.1 {
+            Some(&self.0)
+        } else {
+            None
+        }
+    }
+
+    pub fn contains(&self, pos: &Position) -> bool {
+        *pos == self.0 || *pos == self.1
+    }
+}
+
+/// What the snake needs to know about the board to move
+#[derive(Debug, Clone, Serialize)]
+pub struct Board {
+    #[serde(skip)]
+    pub width: u32,
+    #[serde(skip)]
+    pub height: u32,
+    #[serde(skip)]
+    pub grid: Grid,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub portals: Vec<Portal>,
+}
+
+impl Board {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            grid: Grid::Square,
+            portals: Vec::new(),
         }
     }
+
+    pub fn directions(&self) -> &'static [Direction] {
+        match self.grid {
+            Grid::Square => &SQUARE_DIRECTIONS,
+            Grid::Hex => &HEX_DIRECTIONS,
+        }
+    }
+
+    /// Column/row of the cell where `pos` is drawn (the same on a square grid)
+    pub fn to_offset(&self, pos: &Position) -> Position {
+        match self.grid {
+            Grid::Square => pos.clone(),
+            Grid::Hex => Position {
+                x: pos.x + (pos.y - (pos.y & 1)) / 2,
+                y: pos.y,
+            },
+        }
+    }
+
+    /// Reverse of [`Board::to_offset`]
+    pub fn from_offset(&self, offset: &Position) -> Position {
+        match self.grid {
+            Grid::Square => offset.clone(),
+            Grid::Hex => Position {
+                x: offset.x - (offset.y - (offset.y & 1)) / 2,
+                y: offset.y,
+            },
+        }
+    }
+
+    /// Number of moves between two cells (not taking into account wrapping on a hex grid)
+    pub fn distance(&self, a: &Position, b: &Position) -> u32 {
+        let dx = a.x.abs_diff(b.x);
+        let dy = a.y.abs_diff(b.y);
+        match self.grid {
+            Grid::Square => {
+                dx.min(self.width.saturating_sub(dx)) + dy.min(self.height.saturating_sub(dy))
+            }
+            Grid::Hex => (dx + dy + (a.x + a.y).abs_diff(b.x + b.y)) / 2,
+        }
+    }
+
+    /// Wraps around the edges of the board, then goes through the portal if there is one
+    pub fn resolve(&self, mut pos: Position) -> Position {
+        if self.grid == Grid::Hex {
+            let offset = self.to_offset(&pos);
+            pos = self.from_offset(&Position {
+                x: offset.x.rem_euclid(self.width as i32),
+                y: offset.y.rem_euclid(self.height as i32),
+            });
+        } else if pos.x >= self.width as i32 {
+            pos.x = 0;
+        } else if pos.y >= self.height as i32 {
+            pos.y = 0;
+        } else if pos.y < 0 {
+            pos.y = (self.height - 1) as i32;
+        } else if pos.x < 0 {
+            pos.x = (self.width - 1) as i32;
+        }
+        match self.portals.iter().find_map(|portal| portal.twin(&pos)) {
+            Some(twin) => twin.clone(),
+            None => pos,
+        }
+    }
+
+    pub fn is_portal(&self, pos: &Position) -> bool {
+        self.portals.iter().any(|portal| portal.contains(pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(x: i32, y: i32) -> Position {
+        Position { x, y }
+    }
+
+    #[test]
+    fn should_shrink_down_to_the_min_size() {
+        let mut bounds = Bounds::new(10, 12);
+        assert!(bounds.shrink());
+        assert_eq!(
+            bounds,
+            Bounds {
+                x: 1,
+                y: 1,
+                width: 8,
+                height: 10
+            }
+        );
+        assert!(!bounds.contains(&pos(0, 5)));
+        assert!(!bounds.contains(&pos(9, 5)));
+        assert!(!bounds.contains(&pos(5, 11)));
+        assert!(bounds.contains(&pos(1, 1)));
+        assert!(bounds.contains(&pos(8, 10)));
+
+        assert!(bounds.shrink());
+        assert_eq!(
+            bounds,
+            Bounds {
+                x: 2,
+                y: 2,
+                width: 6,
+                height: 8
+            }
+        );
+        // one more ring would leave less than MIN_BOUNDS_SIZE columns
+        assert!(!bounds.shrink());
+        assert_eq!(bounds.width, 6);
+        assert_eq!(bounds.height, 8);
+    }
+
+    #[test]
+    fn should_teleport_through_the_portals() {
+        let mut board = Board::new(10, 10);
+        board.portals = vec![Portal(pos(3, 3), pos(7, 1)), Portal(pos(0, 5), pos(6, 6))];
+        assert_eq!(board.resolve(pos(3, 3)), pos(7, 1));
+        assert_eq!(board.resolve(pos(7, 1)), pos(3, 3));
+        assert_eq!(board.resolve(pos(4, 4)), pos(4, 4));
+        // wraps around the edge first, lands on the portal
+        assert_eq!(board.resolve(pos(10, 5)), pos(6, 6));
+        assert_eq!(board.resolve(pos(-1, 5)), pos(9, 5));
+    }
+
+    /// Column/row reached from the cell at column/row `offset` when moving towards `dir`
+    fn step(board: &Board, offset: Position, dir: Direction) -> Position {
+        let mut pos = board.from_offset(&offset);
+        pos.move_to_dir(dir);
+        board.to_offset(&board.resolve(pos))
+    }
+
+    fn hex_board() -> Board {
+        let mut board = Board::new(10, 10);
+        board.grid = Grid::Hex;
+        board
+    }
+
+    #[test]
+    fn should_turn_around_the_hex_directions() {
+        let mut dir = Direction::Right;
+        for expected in [
+            Direction::UpRight,
+            Direction::UpLeft,
+            Direction::Left,
+            Direction::DownLeft,
+            Direction::DownRight,
+            Direction::Right,
+        ] {
+            assert_eq!(dir.turn_left().turn_right(), dir);
+            dir = dir.turn_left();
+            assert_eq!(dir, expected);
+        }
+        assert_eq!(Direction::Right.turn_right(), Direction::DownRight);
+        assert_eq!(Direction::Up.turn_left(), Direction::Up);
+        assert_eq!(Direction::Down.turn_right(), Direction::Down);
+    }
+
+    #[test]
+    fn should_move_to_the_hex_neighbours_on_even_and_odd_rows() {
+        let board = hex_board();
+        let even = [
+            (Direction::Right, pos(4, 2)),
+            (Direction::UpRight, pos(3, 1)),
+            (Direction::UpLeft, pos(2, 1)),
+            (Direction::Left, pos(2, 2)),
+            (Direction::DownLeft, pos(2, 3)),
+            (Direction::DownRight, pos(3, 3)),
+        ];
+        for (dir, neighbour) in even {
+            assert_eq!(step(&board, pos(3, 2), dir), neighbour, "{:?}", dir);
+        }
+        let odd = [
+            (Direction::Right, pos(4, 3)),
+            (Direction::UpRight, pos(4, 2)),
+            (Direction::UpLeft, pos(3, 2)),
+            (Direction::Left, pos(2, 3)),
+            (Direction::DownLeft, pos(3, 4)),
+            (Direction::DownRight, pos(4, 4)),
+        ];
+        for (dir, neighbour) in odd {
+            assert_eq!(step(&board, pos(3, 3), dir), neighbour, "{:?}", dir);
+        }
+    }
+
+    #[test]
+    fn should_wrap_around_the_hex_board() {
+        let board = hex_board();
+        assert_eq!(step(&board, pos(9, 4), Direction::Right), pos(0, 4));
+        assert_eq!(step(&board, pos(0, 3), Direction::Left), pos(9, 3));
+        assert_eq!(step(&board, pos(0, 4), Direction::UpLeft), pos(9, 3));
+        assert_eq!(step(&board, pos(9, 3), Direction::DownRight), pos(0, 4));
+        assert_eq!(step(&board, pos(4, 0), Direction::UpRight), pos(4, 9));
+        assert_eq!(step(&board, pos(4, 0), Direction::UpLeft), pos(3, 9));
+        assert_eq!(step(&board, pos(4, 9), Direction::DownLeft), pos(4, 0));
+        assert_eq!(step(&board, pos(4, 9), Direction::DownRight), pos(5, 0));
+    }
 }
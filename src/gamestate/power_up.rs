@@ -0,0 +1,97 @@
+use rand::Rng;
+use serde::Serialize;
+
+use crate::gamestate::physics::Position;
+use crate::gamestate::rng::with_rng;
+
+/// Chances for a power-up to show up on each tick (when there is none on the board)
+const SPAWN_PROBABILITY: f64 = 0.02;
+/// Number of ticks a power-up stays on the board before vanishing
+const LIFETIME: u32 = 60;
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PowerUpKind {
+    /// Doubles the frame duration
+    SlowMotion,
+    /// Lets the snake pass through its own tail
+    Ghost,
+    /// Attracts the fruit towards the head of the snake
+    Magnet,
+}
+
+impl PowerUpKind {
+    /// Number of ticks the effect lasts once picked up
+    pub fn duration(&self) -> u32 {
+        match self {
+            PowerUpKind::SlowMotion => 30,
+            PowerUpKind::Ghost => 20,
+            PowerUpKind::Magnet => 25,
+        }
+    }
+
+    fn random() -> Self {
+        match with_rng(|rng| rng.gen_range(0..3)) {
+            0 => PowerUpKind::SlowMotion,
+            1 => PowerUpKind::Ghost,
+            _ => PowerUpKind::Magnet,
+        }
+    }
+}
+
+/// Power-up lying on the board, waiting to be picked up
+#[derive(Debug, Clone, Serialize)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    pub position: Position,
+    #[serde(skip)]
+    remaining: u32,
+}
+
+impl PowerUp {
+    /// Returns a power-up of a random kind from time to time (at `position`, unless there is no room for it)
+    pub fn maybe_spawn(position: impl FnOnce() -> Option<Position>) -> Option<Self> {
+        if with_rng(|rng| rng.gen_bool(SPAWN_PROBABILITY)) {
+            return position().map(|position| Self {
+                kind: PowerUpKind::random(),
+                position,
+                remaining: LIFETIME,
+            });
+        }
+        None
+    }
+
+    /// Returns false once the power-up has vanished
+    pub fn tick(&mut self) -> bool {
+        self.remaining = self.remaining.saturating_sub(1);
+        self.remaining > 0
+    }
+}
+
+/// Power-up that was picked up and is still active
+#[derive(Debug, Clone, Serialize)]
+pub struct Effect {
+    pub kind: PowerUpKind,
+    /// in ticks
+    pub remaining: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_vanish_after_its_lifetime() {
+        let mut power_up = PowerUp {
+            kind: PowerUpKind::Magnet,
+            position: Position { x: 1, y: 1 },
+            remaining: LIFETIME,
+        };
+        for _ in 1..LIFETIME {
+            assert!(power_up.tick());
+        }
+        assert!(!power_up.tick());
+        assert!(!power_up.tick());
+        assert_eq!(power_up.remaining, 0);
+    }
+}
@@ -0,0 +1,207 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Serialize;
+
+use crate::gamestate::physics::{Board, Direction, Position};
+use crate::gamestate::rng::with_rng;
+use crate::gamestate::snake::Snake;
+
+/// How a CPU snake picks its next move
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Personality {
+    /// Rushes to the fruit, only avoids moves that kill right away
+    Greedy,
+    /// Goes for the fruit but keeps away from dead ends
+    Cautious,
+    /// Wanders around, mostly straight ahead
+    Random,
+}
+
+impl Personality {
+    /// Personalities are handed out in turn, so that each fight is mixed
+    pub fn nth(index: usize) -> Self {
+        match index % 3 {
+            0 => Personality::Greedy,
+            1 => Personality::Cautious,
+            _ => Personality::Random,
+        }
+    }
+}
+
+/// Snake controlled by the cpu, competing for the same fruit as the player
+#[derive(Debug, Serialize)]
+pub struct Rival {
+    pub personality: Personality,
+    #[serde(flatten)]
+    pub snake: Snake,
+}
+
+impl Rival {
+    pub fn new(personality: Personality, snake: Snake) -> Self {
+        Self { personality, snake }
+    }
+
+    /// Picks the direction for the next move - `is_blocked` tells if moving on a cell is deadly
+    pub fn choose_direction(
+        &self,
+        board: &Board,
+        fruit: &Position,
+        is_blocked: impl Fn(&Position) -> bool,
+    ) -> Direction {
+        let current = self.snake.get_direction();
+        let safe_moves: Vec<(Direction, Position)> = board
+            .directions()
+            .iter()
+            .copied()
+            .filter(|dir| *dir != current.opposite())
+            .map(|dir| (dir, self.snake.next_head_pos_towards(dir, board)))
+            .filter(|(_, pos)| !is_blocked(pos))
+            .collect();
+        let closest_to_fruit = |moves: &[(Direction, Position)]| {
+            moves
+                .iter()
+                .min_by_key(|(dir, pos)| (board.distance(pos, fruit), *dir != current))
+                .map(|(dir, _)| *dir)
+        };
+        let picked = match self.personality {
+            Personality::Greedy => closest_to_fruit(&safe_moves),
+            Personality::Cautious => {
+                // a cell with less than 2 free neighbours is likely to be a dead end
+                let roomy_moves: Vec<(Direction, Position)> = safe_moves
+                    .iter()
+                    .filter(|(_, pos)| {
+                        board
+                            .directions()
+                            .iter()
+                            .map(|dir| {
+                                let mut neighbour = pos.clone();
+                                neighbour.move_to_dir(*dir);
+                                board.resolve(neighbour)
+                            })
+                            .filter(|neighbour| !is_blocked(neighbour))
+                            .count()
+                            >= 2
+                    })
+                    .cloned()
+                    .collect();
+                closest_to_fruit(&roomy_moves).or_else(|| closest_to_fruit(&safe_moves))
+            }
+            Personality::Random => with_rng(|rng| {
+                let keep_going = safe_moves.iter().any(|(dir, _)| *dir == current);
+                if keep_going && rng.gen_bool(0.7) {
+                    Some(current)
+                } else {
+                    safe_moves.choose(rng).map(|(dir, _)| *dir)
+                }
+            }),
+        };
+        // trapped: nothing left to do but go straight
+        picked.unwrap_or(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamestate::rng;
+
+    fn pos(x: i32, y: i32) -> Position {
+        Position { x, y }
+    }
+
+    /// Rival with its head in the middle of a 10x10 board, heading down
+    fn rival(personality: Personality) -> Rival {
+        Rival::new(personality, Snake::new(pos(5, 5), 2))
+    }
+
+    #[test]
+    fn should_rush_to_the_fruit_when_greedy() {
+        let board = Board::new(10, 10);
+        let greedy = rival(Personality::Greedy);
+        assert_eq!(
+            greedy.choose_direction(&board, &pos(8, 5), |_| false),
+            Direction::Right
+        );
+        // never turns back, keeps going when no move gets closer
+        assert_eq!(
+            greedy.choose_direction(&board, &pos(5, 2), |_| false),
+            Direction::Down
+        );
+        // right into a dead end
+        let dead_end = [pos(7, 5), pos(6, 4), pos(6, 6)];
+        assert_eq!(
+            greedy.choose_direction(&board, &pos(8, 5), |cell| dead_end.contains(cell)),
+            Direction::Right
+        );
+        assert_eq!(
+            greedy.choose_direction(&board, &pos(8, 5), |cell| *cell == pos(6, 5)),
+            Direction::Down
+        );
+    }
+
+    #[test]
+    fn should_keep_away_from_the_dead_ends_when_cautious() {
+        let board = Board::new(10, 10);
+        let cautious = rival(Personality::Cautious);
+        assert_eq!(
+            cautious.choose_direction(&board, &pos(8, 5), |_| false),
+            Direction::Right
+        );
+        let dead_end = [pos(7, 5), pos(6, 4), pos(6, 6), pos(5, 5)];
+        assert_eq!(
+            cautious.choose_direction(&board, &pos(8, 5), |cell| dead_end.contains(cell)),
+            Direction::Down
+        );
+        // better a dead end than a crash
+        let cornered = [
+            pos(7, 5),
+            pos(6, 4),
+            pos(6, 6),
+            pos(5, 5),
+            pos(4, 5),
+            pos(5, 6),
+        ];
+        assert_eq!(
+            cautious.choose_direction(&board, &pos(8, 5), |cell| cornered.contains(cell)),
+            Direction::Right
+        );
+    }
+
+    #[test]
+    fn should_wander_on_safe_cells_when_random() {
+        rng::seed(42);
+        let board = Board::new(10, 10);
+        let random = rival(Personality::Random);
+        let picked: Vec<Direction> = (0..100)
+            .map(|_| random.choose_direction(&board, &pos(8, 5), |_| false))
+            .collect();
+        assert!(!picked.contains(&Direction::Up));
+        assert!(picked.iter().filter(|dir| **dir == Direction::Down).count() > 50);
+        assert!(picked.contains(&Direction::Left));
+        assert!(picked.contains(&Direction::Right));
+
+        let walls = [pos(5, 6), pos(6, 5)];
+        for _ in 0..20 {
+            assert_eq!(
+                random.choose_direction(&board, &pos(8, 5), |cell| walls.contains(cell)),
+                Direction::Left
+            );
+        }
+    }
+
+    #[test]
+    fn should_go_straight_when_trapped() {
+        let board = Board::new(10, 10);
+        for personality in [
+            Personality::Greedy,
+            Personality::Cautious,
+            Personality::Random,
+        ] {
+            assert_eq!(
+                rival(personality).choose_direction(&board, &pos(8, 5), |_| true),
+                Direction::Down
+            );
+        }
+    }
+}
@@ -0,0 +1,21 @@
+//! Randomness of the game (placements, power-ups, random rivals), from a seed so that a board can be played again
+//! (`gamestate --seed`, `gamestate --ghost`)
+
+use std::cell::RefCell;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Starts over the random numbers generated from `seed`
+pub fn seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// Calls `f` with the random number generator of the game
+pub fn with_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
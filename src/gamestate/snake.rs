@@ -1,7 +1,7 @@
 use serde::Serialize;
 use std::collections::LinkedList;
 
-use crate::gamestate::physics::{Direction, Position};
+use crate::gamestate::physics::{Board, Direction, Position};
 
 #[derive(Debug, Serialize)]
 pub struct Snake {
@@ -32,28 +32,13 @@ impl Snake {
         }
     }
 
-    pub fn update(&mut self, height: u32, width: u32) {
+    pub fn update(&mut self, board: &Board) {
         if self.tail.len() > 0 {
             self.tail.push_front(self.head.clone());
             self.tail.pop_back();
         }
 
-        match self.direction {
-            Direction::Up => self.head.y -= 1,
-            Direction::Right => self.head.x += 1,
-            Direction::Down => self.head.y += 1,
-            Direction::Left => self.head.x -= 1,
-        }
-
-        if self.head.x >= height as i32 {
-            self.head.x = 0;
-        } else if self.head.y >= width as i32 {
-            self.head.y = 0;
-        } else if self.head.y < 0 {
-            self.head.y = (width - 1) as i32;
-        } else if self.head.x < 0 {
-            self.head.x = (height - 1) as i32;
-        }
+        self.head = self.next_head_pos(board);
 
         self.updated_tail_pos = true;
     }
@@ -67,6 +52,18 @@ impl Snake {
         self.updated_tail_pos = false;
     }
 
+    /// Unlike [`Snake::set_dir`] (which waits for the snake to move before accepting a new turn),
+    /// applies right away - used by the cpu that steers once per move
+    pub fn steer(&mut self, dir: Direction) {
+        if dir != self.direction.opposite() {
+            self.direction = dir;
+        }
+    }
+
+    pub fn get_direction(&self) -> Direction {
+        self.direction
+    }
+
     pub fn get_head_pos(&self) -> &Position {
         &self.head
     }
@@ -75,6 +72,11 @@ impl Snake {
         &self.tail.len() - self.initial_length as usize
     }
 
+    /// true if the head or the tail is on `pos`
+    pub fn contains(&self, pos: &Position) -> bool {
+        self.head == *pos || self.tail.iter().any(|tail_pos| tail_pos == pos)
+    }
+
     pub fn is_tail_overlapping(&self) -> bool {
         for pos in self.tail.iter() {
             if *pos == self.head {
@@ -85,8 +87,8 @@ impl Snake {
         false
     }
 
-    pub fn will_tail_overlap(&self) -> bool {
-        let next = self.next_head_pos();
+    pub fn will_tail_overlap(&self, board: &Board) -> bool {
+        let next = self.next_head_pos(board);
 
         for pos in self.tail.iter() {
             if *pos == next {
@@ -106,16 +108,17 @@ impl Snake {
         self.tail.push_back(last);
     }
 
-    fn next_head_pos(&self) -> Position {
+    /// Where the head will be after the next update (wrapping around the board, going through portals)
+    pub fn next_head_pos(&self, board: &Board) -> Position {
+        self.next_head_pos_towards(self.direction, board)
+    }
+
+    /// Where the head would be after the next update if it went towards `dir`
+    pub fn next_head_pos_towards(&self, dir: Direction, board: &Board) -> Position {
         let mut pos = self.head.clone();
 
-        match self.direction {
-            Direction::Up => pos.y -= 1,
-            Direction::Left => pos.x -= 1,
-            Direction::Down => pos.y += 1,
-            Direction::Right => pos.x += 1,
-        }
+        pos.move_to_dir(dir);
 
-        pos
+        board.resolve(pos)
     }
 }
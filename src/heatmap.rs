@@ -0,0 +1,342 @@
+//! Heatmap of a recording (`snakepipe heatmap`): how many times each cell was visited by the head of the snake,
+//! or got a fruit
+//!
+//! The cells are the columns/rows of the board (the same as the positions on a square grid).
+
+use std::io::Write;
+use std::path::Path;
+
+use array2d::Array2D;
+use clap::ValueEnum;
+
+use crate::input::{
+    parse_gamestate_from, Frame, Game, GameState, Grid, InitOptions, ParseOptions, PowerUpKind,
+};
+use crate::recording::input_name;
+use crate::render::{render_board, Point, RenderGrid};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum HeatmapFormat {
+    /// Shaded blocks, drawn like `render` does
+    Terminal,
+    /// One row per cell: column, row, head visits and fruit spawns
+    Csv,
+    Png,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Layer {
+    /// Cells where the head of the snake was, on each tick while running
+    Head,
+    /// Cells where a fruit showed up
+    Fruit,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HeatmapOptions {
+    pub format: HeatmapFormat,
+    /// Which counts are drawn (the csv has both)
+    pub layer: Layer,
+    /// Size of a cell in pixels (png)
+    pub cell_size: u32,
+}
+
+/// Counts per cell, indexed by row then column
+struct Heatmap {
+    head: Array2D<u32>,
+    fruit: Array2D<u32>,
+    grid: Grid,
+}
+
+impl Heatmap {
+    fn new(options: &InitOptions) -> Self {
+        let (rows, columns) = (options.size.height as usize, options.size.width as usize);
+        Self {
+            head: Array2D::filled_with(0, rows, columns),
+            fruit: Array2D::filled_with(0, rows, columns),
+            grid: options.grid,
+        }
+    }
+
+    fn layer(&self, layer: Layer) -> &Array2D<u32> {
+        match layer {
+            Layer::Head => &self.head,
+            Layer::Fruit => &self.fruit,
+        }
+    }
+
+    /// Adds `game`, `previous` being the game before it in the session
+    fn add(&mut self, game: &Game, previous: Option<&Game>) {
+        if game.state == GameState::Running {
+            increment(&mut self.head, self.grid.to_offset(&game.snake.head));
+        }
+        // a fruit spawns when it shows up elsewhere (eaten by the snake or a rival, walled by the shrinking arena …)
+        // unless it was attracted by the magnet, when eaten (even if it respawned in place) and on a new game
+        let spawned = previous.is_none_or(|previous| {
+            (game.fruit != previous.fruit && !attracted(game, previous))
+                || game.score > previous.score
+                || game.restarted_from(previous)
+        });
+        if spawned {
+            increment(&mut self.fruit, self.grid.to_offset(&game.fruit));
+        }
+    }
+}
+
+/// The fruit moved by one cell, towards the head (magnet effect)
+fn attracted(game: &Game, previous: &Game) -> bool {
+    let magnet = game
+        .effects
+        .iter()
+        .any(|effect| effect.kind == PowerUpKind::Magnet);
+    let distance =
+        (game.fruit.x - previous.fruit.x).abs() + (game.fruit.y - previous.fruit.y).abs();
+    magnet && distance == 1
+}
+
+fn increment(counts: &mut Array2D<u32>, (x, y): (i32, i32)) {
+    if x >= 0 && y >= 0 {
+        if let Some(count) = counts.get_mut(y as usize, x as usize) {
+            *count += 1;
+        }
+    }
+}
+
+fn max(counts: &Array2D<u32>) -> u32 {
+    counts.elements_row_major_iter().copied().max().unwrap_or(0)
+}
+
+/// Shaded blocks, from the lightest (a quarter of the max or less) to the darkest
+fn format_terminal(heatmap: &Heatmap, layer: Layer) -> String {
+    let counts = heatmap.layer(layer);
+    let max = max(counts);
+    let mut grid = RenderGrid::new(
+        counts.num_columns() as u32,
+        counts.num_rows() as u32,
+        heatmap.grid,
+    );
+    for (y, row) in counts.rows_iter().enumerate() {
+        for (x, count) in row.enumerate() {
+            if *count > 0 {
+                grid.set(x, y, Point::Heat((*count * 4).div_ceil(max) as u8));
+            }
+        }
+    }
+    let title = match layer {
+        Layer::Head => "Head visits",
+        Layer::Fruit => "Fruit spawns",
+    };
+    let total: u32 = counts.elements_row_major_iter().sum();
+    let mut lines = vec![format!(
+        "{} - {} in total, up to {} in a cell",
+        title, total, max
+    )];
+    lines.extend(render_board(&grid, counts.num_columns() as u32, false));
+    lines.push("· none  ░ up to 25%  ▒ up to 50%  ▓ up to 75%  █ up to 100%".to_string());
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+fn format_csv(heatmap: &Heatmap) -> String {
+    let mut csv = "column,row,head,fruit\n".to_string();
+    for y in 0..heatmap.head.num_rows() {
+        for x in 0..heatmap.head.num_columns() {
+            csv.push_str(&format!(
+                "{},{},{},{}\n",
+                x,
+                y,
+                heatmap.head[(y, x)],
+                heatmap.fruit[(y, x)]
+            ));
+        }
+    }
+    csv
+}
+
+/// From dark gray (never visited) through red and yellow to white (the most visited)
+fn color(count: u32, max: u32) -> [u8; 3] {
+    if count == 0 {
+        return [32, 32, 32];
+    }
+    let heat = count as f64 / max as f64 * 3.0;
+    [heat, heat - 1.0, heat - 2.0].map(|channel| (channel.clamp(0.0, 1.0) * 255.0) as u8)
+}
+
+fn write_png<W: Write>(
+    heatmap: &Heatmap,
+    layer: Layer,
+    cell_size: u32,
+    writer: W,
+) -> Result<(), png::EncodingError> {
+    let counts = heatmap.layer(layer);
+    let max = max(counts);
+    // odd rows of a hex grid are shifted by half a cell
+    let shift = match heatmap.grid {
+        Grid::Square => 0,
+        Grid::Hex => cell_size / 2,
+    };
+    let width = counts.num_columns() as u32 * cell_size + shift;
+    let height = counts.num_rows() as u32 * cell_size;
+    let mut pixels = vec![0; (width * height * 3) as usize];
+    for (y, row) in counts.rows_iter().enumerate() {
+        let offset = if y % 2 == 1 { shift } else { 0 };
+        for (x, count) in row.enumerate() {
+            let color = color(*count, max);
+            for dy in 0..cell_size {
+                for dx in 0..cell_size {
+                    let px = offset + x as u32 * cell_size + dx;
+                    let py = y as u32 * cell_size + dy;
+                    let index = ((py * width + px) * 3) as usize;
+                    pixels[index..index + 3].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)
+}
+
+/// Reads the whole input (all its sessions must share the same board) and outputs its heatmap
+pub fn run(
+    input_path: Option<&Path>,
+    heatmap_options: HeatmapOptions,
+    mut output: Box<dyn Write>,
+    parse_options: ParseOptions,
+) {
+    let input = match parse_gamestate_from(input_path, parse_options) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!(
+                "Error occurred while parsing {}: \"{}\"",
+                input_name(input_path),
+                e
+            );
+            std::process::exit(exitcode::DATAERR);
+        }
+    };
+    let options = input.options;
+    let mut heatmap = Heatmap::new(&options);
+    let mut previous: Option<Game> = None;
+    for frame in input.lines {
+        match frame {
+            Frame::Options(new_options) => {
+                if new_options.size.width != options.size.width
+                    || new_options.size.height != options.size.height
+                    || new_options.grid != options.grid
+                {
+                    eprintln!(
+                        "Error: the sessions of {} don't share the same board, expected {}x{} ({:?})",
+                        input_name(input_path),
+                        options.size.width,
+                        options.size.height,
+                        options.grid
+                    );
+                    std::process::exit(exitcode::DATAERR);
+                }
+                previous = None;
+            }
+            Frame::Game(game) => {
+                heatmap.add(&game, previous.as_ref());
+                previous = Some(game);
+            }
+        }
+    }
+    let result = match heatmap_options.format {
+        HeatmapFormat::Terminal => {
+            output.write_all(format_terminal(&heatmap, heatmap_options.layer).as_bytes())
+        }
+        HeatmapFormat::Csv => output.write_all(format_csv(&heatmap).as_bytes()),
+        HeatmapFormat::Png => write_png(
+            &heatmap,
+            heatmap_options.layer,
+            heatmap_options.cell_size,
+            &mut output,
+        )
+        .map_err(std::io::Error::other),
+    };
+    if let Err(e) = result.and_then(|_| output.flush()) {
+        eprintln!("Error occurred while writing: \"{}\"", e);
+        std::process::exit(exitcode::IOERR);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTIONS: &str = r#"{"frameDuration":100,"size":{"width":10,"height":10},"featuresWithVersion":{},"metadatas":{}}"#;
+
+    /// The fields after the head and the fruit: score, state …
+    fn game(head: (i32, i32), fruit: (i32, i32), rest: &str) -> Game {
+        serde_json::from_str(&format!(
+            r#"{{"snake":{{"direction":"right","head":{{"x":{},"y":{}}},"tail":[]}},"fruit":{{"x":{},"y":{}}},{}}}"#,
+            head.0, head.1, fruit.0, fruit.1, rest
+        ))
+        .unwrap()
+    }
+
+    fn heatmap(games: &[Game]) -> Heatmap {
+        let mut heatmap = Heatmap::new(&serde_json::from_str(OPTIONS).unwrap());
+        for (index, game) in games.iter().enumerate() {
+            heatmap.add(game, index.checked_sub(1).map(|index| &games[index]));
+        }
+        heatmap
+    }
+
+    fn count(counts: &Array2D<u32>, (x, y): (usize, usize)) -> u32 {
+        counts[(y, x)]
+    }
+
+    #[test]
+    fn should_count_the_visits_while_running() {
+        let heatmap = heatmap(&[
+            game((1, 1), (9, 9), r#""score":0,"state":"running""#),
+            game((2, 1), (9, 9), r#""score":0,"state":"running""#),
+            game((2, 1), (9, 9), r#""score":0,"state":"paused""#),
+            game((2, 1), (9, 9), r#""score":0,"state":"running""#),
+            game((2, 1), (9, 9), r#""score":0,"state":"over""#),
+        ]);
+        assert_eq!(count(&heatmap.head, (1, 1)), 1);
+        assert_eq!(count(&heatmap.head, (2, 1)), 2);
+        assert_eq!(heatmap.head.elements_row_major_iter().sum::<u32>(), 3);
+    }
+
+    #[test]
+    fn should_count_each_fruit_spawn_once() {
+        let magnet = r#""effects":[{"kind":"magnet","remaining":3}]"#;
+        let heatmap = heatmap(&[
+            // first fruit
+            game((1, 1), (5, 5), r#""score":0,"state":"running""#),
+            game((2, 1), (5, 5), r#""score":0,"state":"running""#),
+            // eaten by a rival
+            game((3, 1), (7, 2), r#""score":0,"state":"running""#),
+            // attracted by the magnet
+            game(
+                (4, 1),
+                (6, 2),
+                &format!(r#""score":0,"state":"running",{}"#, magnet),
+            ),
+            game(
+                (5, 1),
+                (5, 2),
+                &format!(r#""score":0,"state":"running",{}"#, magnet),
+            ),
+            // eaten by the snake, respawned next to it
+            game(
+                (5, 2),
+                (5, 3),
+                &format!(r#""score":1,"state":"running",{}"#, magnet),
+            ),
+            game((5, 3), (5, 3), r#""score":1,"state":"over""#),
+            // new game, with the fruit where it was
+            game((1, 1), (5, 3), r#""score":0,"state":"running""#),
+        ]);
+        assert_eq!(count(&heatmap.fruit, (5, 5)), 1);
+        assert_eq!(count(&heatmap.fruit, (7, 2)), 1);
+        assert_eq!(count(&heatmap.fruit, (6, 2)), 0);
+        assert_eq!(count(&heatmap.fruit, (5, 2)), 0);
+        assert_eq!(count(&heatmap.fruit, (5, 3)), 2);
+        assert_eq!(heatmap.fruit.elements_row_major_iter().sum::<u32>(), 4);
+    }
+}
@@ -1,53 +1,196 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt;
-use std::io::{stdin, BufRead, Lines};
+use std::io::{BufRead, Lines};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use futures_util::{future, stream, Stream, StreamExt};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::codec::{self, Decoder};
+use crate::recording;
 
 // options
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, JsonSchema)]
 pub struct SizeOption {
     pub width: u32,
     pub height: u32,
 }
 
+/// Layout of the cells of the board
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Grid {
+    #[default]
+    Square,
+    /// Rows of hexagons, odd rows shifted by half a cell ("odd-r" layout),
+    /// the positions in the frames are axial coordinates (`x` is q, `y` is r)
+    Hex,
+}
+
+impl Grid {
+    pub fn is_square(&self) -> bool {
+        *self == Grid::Square
+    }
+
+    /// Column/row of the cell where `position` is drawn
+    pub fn to_offset(&self, position: &Position) -> (i32, i32) {
+        match self {
+            Grid::Square => (position.x, position.y),
+            Grid::Hex => (position.x + (position.y - (position.y & 1)) / 2, position.y),
+        }
+    }
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
 /// Holds the options that were passed to the cli with a flag
 /// that are relevent for rendering the game.
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct InitOptions {
     pub frame_duration: u32,
-    #[serde(skip)]
+    /// The options of the gamestate below are recorded so that a run can be raced again (`gamestate --ghost`)
+    #[serde(default, skip_serializing_if = "is_zero")]
     pub snake_length: u32,
+    /// Shrinking arena mode: number of ticks between each shrink of the playable area
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shrink_every: Option<u32>,
+    /// Number of pairs of portals randomly placed on the board
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub portals: u32,
+    /// Portals of the map loaded with `--map`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub map_portals: Vec<Portal>,
+    /// Number of snakes controlled by the cpu
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub rivals: u32,
+    /// Power-ups randomly show up on the board
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub power_ups: bool,
     pub size: SizeOption,
+    #[serde(default, skip_serializing_if = "Grid::is_square")]
+    pub grid: Grid,
+    /// Seed of the random placements of `gamestate` (snake, fruit, portals …): the same seed gives the same board
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
     pub features_with_version: std::collections::HashMap<String, String>,
     pub metadatas: std::collections::HashMap<String, String>,
+    /// Fields unknown to this version (added by a newer producer or another implementation), re-emitted unchanged
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 // gamestate
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum Direction {
     Up,
     Right,
     Down,
     Left,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// How a snake controlled by the cpu picks its moves
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Personality {
+    Greedy,
+    Cautious,
+    Random,
+}
+
+/// Snake controlled by the cpu, competing for the fruit
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+pub struct Rival {
+    pub personality: Personality,
+    #[serde(flatten)]
+    pub snake: Snake,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum PowerUpKind {
+    SlowMotion,
+    Ghost,
+    Magnet,
+}
+
+impl fmt::Display for PowerUpKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let str = match self {
+            Self::SlowMotion => "Slow-motion",
+            Self::Ghost => "Ghost",
+            Self::Magnet => "Magnet",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+/// Power-up lying on the board, waiting to be picked up
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    pub position: Position,
+}
+
+/// Power-up that was picked up and is still active
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+pub struct Effect {
+    pub kind: PowerUpKind,
+    /// in ticks
+    pub remaining: u32,
+}
+
+/// Playable area of the board (shrinking arena mode) - the cells outside are walls
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, JsonSchema)]
+pub struct Bounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Bounds {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x
+            && y >= self.y
+            && x < self.x + self.width as i32
+            && y < self.y + self.height as i32
+    }
+}
+
+/// Pair of cells teleporting the head of the snake from one to the other
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+pub struct Portal(pub Position, pub Position);
+
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct Snake {
     pub direction: Direction,
     pub head: Position,
     pub tail: Vec<Position>,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum GameState {
     Paused,
@@ -67,43 +210,216 @@ impl fmt::Display for GameState {
 }
 
 /// Holds the state of the game at any time
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct Game {
     pub snake: Snake,
     pub fruit: Position,
     pub score: u32,
     pub state: GameState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<Bounds>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub portals: Vec<Portal>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rivals: Vec<Rival>,
+    #[serde(rename = "powerUp", default, skip_serializing_if = "Option::is_none")]
+    pub power_up: Option<PowerUp>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub effects: Vec<Effect>,
+    /// Snake of a previous run replayed in lockstep (`gamestate --ghost`), nothing collides with it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ghost: Option<Snake>,
+    /// ms since the start of the game, replays use it to reproduce the original timing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed: Option<u64>,
+    /// Fields unknown to this version (added by a newer producer or another implementation), re-emitted unchanged
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Game {
+    /// Whether the game started over after `previous` (the game before it in the session):
+    /// the score dropped, or it is not over anymore
+    pub fn restarted_from(&self, previous: &Game) -> bool {
+        self.score < previous.score
+            || (previous.state == GameState::Over && self.state != GameState::Over)
+    }
+}
+
+/// A line of the stream, after the first header
+///
+/// A new header can show up at any point of the stream: it starts a new session (new size, new options ...)
+#[derive(Debug, Serialize, Clone, JsonSchema)]
+#[serde(untagged)]
+pub enum Frame {
+    Options(InitOptions),
+    Game(Game),
+}
+
+impl Frame {
+    /// Parses a line as [`Game`], falling back to [`InitOptions`] (the error reported is the one from [`Game`])
+    pub(crate) fn parse(line: &str) -> Result<Frame, serde_json::Error> {
+        serde_json::from_str::<Game>(line)
+            .map(Frame::Game)
+            .or_else(|e| {
+                serde_json::from_str::<InitOptions>(line)
+                    .map(Frame::Options)
+                    .map_err(|_| e)
+            })
+    }
+}
+
+/// How [`Input`] deals with the lines that can't be parsed as [`Game`] (or [`InitOptions`])
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions {
+    /// Report each skipped line on stderr (line number, serde error and offending text),
+    /// otherwise they are silently dropped
+    pub strict: bool,
+}
+
+/// Counters updated while the lines of an [`Input`] are consumed
+///
+/// Cheap to clone (shared counters), so it can be read from elsewhere than where the lines are iterated.
+#[derive(Debug, Clone, Default)]
+pub struct ParseStats {
+    lines_read: Arc<AtomicUsize>,
+    skipped_lines: Arc<AtomicUsize>,
+    first_error: Arc<OnceLock<String>>,
+}
+
+impl ParseStats {
+    /// Number of lines read so far (including the header)
+    pub fn lines_read(&self) -> usize {
+        self.lines_read.load(Ordering::Relaxed)
+    }
+
+    /// Number of lines that could not be read or parsed as [`Frame`]
+    pub fn skipped_lines(&self) -> usize {
+        self.skipped_lines.load(Ordering::Relaxed)
+    }
+
+    /// The report of the first line skipped, as printed on stderr (only kept in strict mode)
+    pub fn first_error(&self) -> Option<&str> {
+        self.first_error.get().map(String::as_str)
+    }
+
+    /// Counts the skipped line, reporting it on stderr in strict mode
+    fn skipped(&self, report: String, parse_options: ParseOptions) {
+        self.skipped_lines.fetch_add(1, Ordering::Relaxed);
+        if parse_options.strict {
+            eprintln!("{}\r", report);
+            self.first_error.get_or_init(|| report);
+        }
+    }
+
+    /// Counts the frame decoded from a binary recording (reporting the error in strict mode)
+    fn decoded_frame(
+        &self,
+        result_frame: std::io::Result<Frame>,
+        parse_options: ParseOptions,
+    ) -> Option<Frame> {
+        let record_number = self.lines_read.fetch_add(1, Ordering::Relaxed) + 1;
+        match result_frame {
+            Ok(frame) => Some(frame),
+            Err(e) => {
+                self.skipped(
+                    format!("Skipped record {}: {}", record_number, e),
+                    parse_options,
+                );
+                None
+            }
+        }
+    }
+
+    /// Parses the next line of the stream as [`Frame`], counting (and reporting in strict mode) the ones that are skipped
+    fn parse_line(
+        &self,
+        result_line: std::io::Result<String>,
+        parse_options: ParseOptions,
+    ) -> Option<Frame> {
+        let line_number = self.lines_read.fetch_add(1, Ordering::Relaxed) + 1;
+        let error = match result_line {
+            Ok(line) => match Frame::parse(&line) {
+                Ok(parsed_line) => return Some(parsed_line),
+                Err(e) => format!("{} - {}", e, line),
+            },
+            Err(e) => e.to_string(),
+        };
+        self.skipped(
+            format!("Skipped line {}: {}", line_number, error),
+            parse_options,
+        );
+        None
+    }
 }
 
 /// Accepts the iterator from [`std::io::stdin()`]`.line()`
 /// - parses the first line into `options` as [`InitOptions`]
-/// - returns an iterator of [`Game`] inside `lines` (already parsed)
+/// - returns an iterator of [`Frame`] inside `lines` (already parsed): mostly [`Game`], or [`InitOptions`] when a new session starts
+/// - keeps track of the lines that were skipped in `stats`
 ///
 /// Used by [`parse_gamestate`] under the hood.
 pub struct Input {
     pub options: InitOptions,
-    pub lines: Box<dyn Iterator<Item = Game>>, // std::io::Lines<T>, //Lines<T>,
+    pub lines: Box<dyn Iterator<Item = Frame>>, // std::io::Lines<T>, //Lines<T>,
+    pub stats: ParseStats,
 }
 
 impl Input {
     /// Creates a input from a buffer (could be from [`std::io::stdin()`]`.line()`)
-    pub fn new<T: BufRead + 'static>(
+    pub fn new<T: BufRead + 'static>(lines: Lines<T>) -> Result<Input, Box<dyn std::error::Error>> {
+        Self::with_options(lines, ParseOptions::default())
+    }
+
+    /// Creates a input from a reader, detecting its format: NDJSON or binary (see [`crate::codec`])
+    ///
+    /// In the binary format, the stream stops at the first record that can't be decoded.
+    pub fn from_reader<T: BufRead + 'static>(
+        mut reader: T,
+        parse_options: ParseOptions,
+    ) -> Result<Input, Box<dyn std::error::Error>> {
+        if !codec::is_binary(reader.fill_buf()?) {
+            return Self::with_options(reader.lines(), parse_options);
+        }
+        let mut decoder = Decoder::new(reader)?;
+        match decoder.next() {
+            Some(Ok(Frame::Options(options))) => {
+                let stats = ParseStats::default();
+                stats.lines_read.fetch_add(1, Ordering::Relaxed);
+                let counters = stats.clone();
+                let decoded_frames = decoder.map_while(move |result_frame| {
+                    counters.decoded_frame(result_frame, parse_options)
+                });
+                Ok(Self {
+                    options,
+                    lines: Box::new(decoded_frames),
+                    stats,
+                })
+            }
+            Some(Ok(Frame::Game(_))) => Err("Recording doesn't start with a header".into()),
+            None => Err("Buffer is empty".into()),
+            Some(Err(e)) => Err(e.into()),
+        }
+    }
+
+    /// Same as [`Input::new`], the lines that can't be parsed are handled according to `parse_options`
+    pub fn with_options<T: BufRead + 'static>(
         mut lines: Lines<T>,
+        parse_options: ParseOptions,
     ) -> Result<Input, Box<dyn std::error::Error>> {
         match lines.next() {
             Some(Ok(first_line)) => {
                 let options: InitOptions = serde_json::from_str(&first_line)?;
+                let stats = ParseStats::default();
+                stats.lines_read.fetch_add(1, Ordering::Relaxed);
+                let counters = stats.clone();
                 // flat_map keeps Some and extracts their values while removing Err - we ignore parse errors on lines / we dont panic on it
-                let parsed_lines = lines.flat_map(|result_line| match result_line {
-                    Ok(line) => match serde_json::from_str::<Game>(&line) {
-                        Ok(parsed_line) => Some(parsed_line),
-                        Err(_) => None,
-                    },
-                    Err(_) => None,
-                });
+                let parsed_lines = lines
+                    .flat_map(move |result_line| counters.parse_line(result_line, parse_options));
                 Ok(Self {
                     options,
                     lines: Box::new(parsed_lines),
+                    stats,
                 })
             }
             None => Err("Buffer is empty".into()),
@@ -112,11 +428,91 @@ impl Input {
     }
 }
 
+/// Same as [`Input`] for async consumers: accepts any [`AsyncBufRead`] (like [`tokio::io::stdin()`])
+/// - parses the first line into `options` as [`InitOptions`] up front
+/// - returns a [`Stream`] of [`Frame`] inside `lines` (already parsed), reading doesn't block the runtime
+///
+/// Used by [`parse_gamestate_async`] under the hood.
+pub struct AsyncInput {
+    pub options: InitOptions,
+    pub lines: Pin<Box<dyn Stream<Item = Frame> + Send>>,
+    pub stats: ParseStats,
+}
+
+impl AsyncInput {
+    /// Creates a input from an async buffer, the lines that can't be parsed are handled according to `parse_options`
+    pub async fn new<T: AsyncBufRead + Unpin + Send + 'static>(
+        reader: T,
+        parse_options: ParseOptions,
+    ) -> Result<AsyncInput, Box<dyn std::error::Error>> {
+        let mut lines = reader.lines();
+        match lines.next_line().await {
+            Ok(Some(first_line)) => {
+                let options: InitOptions = serde_json::from_str(&first_line)?;
+                let stats = ParseStats::default();
+                stats.lines_read.fetch_add(1, Ordering::Relaxed);
+                let counters = stats.clone();
+                let raw_lines = stream::unfold(lines, |mut lines| async move {
+                    // stops at the end of the stream, read errors are passed along to be counted as skipped lines
+                    match lines.next_line().await {
+                        Ok(Some(line)) => Some((Ok(line), lines)),
+                        Ok(None) => None,
+                        Err(e) => Some((Err(e), lines)),
+                    }
+                });
+                let parsed_lines = raw_lines.filter_map(move |result_line| {
+                    future::ready(counters.parse_line(result_line, parse_options))
+                });
+                Ok(Self {
+                    options,
+                    lines: Box::pin(parsed_lines),
+                    stats,
+                })
+            }
+            Ok(None) => Err("Buffer is empty".into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Reads the recording at `path` with [`parse_gamestate_from`] in a dedicated thread
+    /// (files, which may be compressed, can't be read asynchronously), the frames are sent back through a channel
+    pub async fn from_path(
+        path: PathBuf,
+        parse_options: ParseOptions,
+    ) -> Result<AsyncInput, Box<dyn std::error::Error>> {
+        let (header_tx, header_rx) = oneshot::channel();
+        let (tx, rx) = mpsc::channel::<Frame>(64);
+        std::thread::spawn(
+            move || match parse_gamestate_from(Some(&path), parse_options) {
+                Ok(input) => {
+                    let _ = header_tx.send(Ok((input.options, input.stats)));
+                    for frame in input.lines {
+                        if tx.blocking_send(frame).is_err() {
+                            break;
+                        }
+                    }
+                }
+                Err(e) => {
+                    let _ = header_tx.send(Err(e.to_string()));
+                }
+            },
+        );
+        match header_rx.await? {
+            Ok((options, stats)) => Ok(Self {
+                options,
+                lines: Box::pin(ReceiverStream::new(rx)),
+                stats,
+            }),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
 /// Parses the stdin containing the gamestate
 ///
 /// Example:
 /// ```
-/// use snakepipe::input::{parse_gamestate, Game};
+/// use snakepipe::input::{parse_gamestate, Frame, Game};
 ///
 /// fn main() -> () {
 ///     match parse_gamestate() {
@@ -128,8 +524,12 @@ impl Input {
 ///                 input.options.size.width,
 ///                 input.options.size.height
 ///             );
-///             for parsed_line in input.lines {
-///                 do_something(parsed_line);
+///             for frame in input.lines {
+///                 match frame {
+///                     Frame::Game(parsed_line) => do_something(parsed_line),
+///                     // a new session started (the size of the board may have changed)
+///                     Frame::Options(options) => println!("New session {:?}", options.size),
+///                 }
 ///             }
 ///         }
 ///         Err(e) => {
@@ -143,10 +543,204 @@ impl Input {
 /// }
 /// ```
 ///
+/// Recordings in the binary format (see [`crate::codec`]) are detected and decoded.
+///
 /// If you want to parse from elsewhere than stdin, you can use [Input]
 pub fn parse_gamestate() -> Result<Input, Box<dyn std::error::Error>> {
+    parse_gamestate_with(ParseOptions::default())
+}
+
+/// Same as [`parse_gamestate`], the lines that can't be parsed are handled according to `parse_options`
+pub fn parse_gamestate_with(
+    parse_options: ParseOptions,
+) -> Result<Input, Box<dyn std::error::Error>> {
     // todo couldn't find how to peek into the input (to know if it comes from `snake gamestate` or `cat /some-file`), without consuming it
     // so we'll show a "Replay" message when `gamestate throttle` is used in the pipeline (even if it could only be used to throttle directly `gamestate`)
-    let lines = stdin().lines();
-    Input::new(lines)
+    parse_gamestate_from(None, parse_options)
+}
+
+/// Same as [`parse_gamestate_with`], reading the recording at `path` (stdin when `None`)
+///
+/// Recordings compressed with gzip or zstd are decompressed.
+pub fn parse_gamestate_from(
+    path: Option<&Path>,
+    parse_options: ParseOptions,
+) -> Result<Input, Box<dyn std::error::Error>> {
+    Input::from_reader(recording::open_input(path)?, parse_options)
+}
+
+/// Same as [`parse_gamestate_with`], reading stdin asynchronously (to be used inside a tokio runtime)
+pub async fn parse_gamestate_async(
+    parse_options: ParseOptions,
+) -> Result<AsyncInput, Box<dyn std::error::Error>> {
+    AsyncInput::new(BufReader::new(tokio::io::stdin()), parse_options).await
+}
+
+/// Same as [`parse_gamestate_async`], reading the recording at `path` (stdin when `None`), see [`parse_gamestate_from`]
+pub async fn parse_gamestate_async_from(
+    path: Option<&Path>,
+    parse_options: ParseOptions,
+) -> Result<AsyncInput, Box<dyn std::error::Error>> {
+    match path {
+        Some(path) => AsyncInput::from_path(path.to_path_buf(), parse_options).await,
+        None => parse_gamestate_async(parse_options).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const HEADER: &str = r#"{"frameDuration":100,"size":{"width":10,"height":10},"featuresWithVersion":{},"metadatas":{}}"#;
+
+    fn game(score: u32) -> String {
+        format!(
+            r#"{{"snake":{{"direction":"right","head":{{"x":1,"y":1}},"tail":[]}},"fruit":{{"x":9,"y":9}},"score":{},"state":"running"}}"#,
+            score
+        )
+    }
+
+    fn input(lines: &[&str], parse_options: ParseOptions) -> Input {
+        Input::with_options(BufRead::lines(Cursor::new(lines.join("\n"))), parse_options).unwrap()
+    }
+
+    fn scores(frames: impl IntoIterator<Item = Frame>) -> Vec<Option<u32>> {
+        frames
+            .into_iter()
+            .map(|frame| match frame {
+                Frame::Game(game) => Some(game.score),
+                Frame::Options(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_count_the_skipped_lines() {
+        for strict in [false, true] {
+            let input = input(
+                &[HEADER, &game(0), "not json", &game(10), r#"{"score":"x"}"#],
+                ParseOptions { strict },
+            );
+            let stats = input.stats.clone();
+            assert_eq!(stats.lines_read(), 1);
+            assert_eq!(scores(input.lines), vec![Some(0), Some(10)]);
+            assert_eq!(stats.lines_read(), 5);
+            assert_eq!(stats.skipped_lines(), 2);
+        }
+    }
+
+    #[test]
+    fn should_keep_the_first_error_in_strict_mode() {
+        let lines = [HEADER, &game(0), "not json", "{}"];
+        let input = input(&lines, ParseOptions { strict: true });
+        let stats = input.stats.clone();
+        input.lines.for_each(drop);
+        let first_error = stats.first_error().unwrap();
+        assert!(
+            first_error.starts_with("Skipped line 3: "),
+            "{}",
+            first_error
+        );
+        assert!(first_error.ends_with(" - not json"), "{}", first_error);
+
+        let input = self::input(&lines, ParseOptions::default());
+        let stats = input.stats.clone();
+        input.lines.for_each(drop);
+        assert_eq!(stats.skipped_lines(), 2);
+        assert_eq!(stats.first_error(), None);
+    }
+
+    fn to_lines(frames: impl IntoIterator<Item = Frame>) -> Vec<String> {
+        frames
+            .into_iter()
+            .map(|frame| serde_json::to_string(&frame).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn should_stream_the_same_frames_as_input() {
+        let lines = [HEADER, &game(0), "not json", HEADER, &game(10), &game(20)];
+        let input = input(&lines, ParseOptions::default());
+
+        let async_input = AsyncInput::new(
+            BufReader::new(Cursor::new(lines.join("\n"))),
+            ParseOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            serde_json::to_string(&async_input.options).unwrap(),
+            serde_json::to_string(&input.options).unwrap()
+        );
+        let frames: Vec<Frame> = async_input.lines.collect().await;
+        assert_eq!(async_input.stats.skipped_lines(), 1);
+        assert_eq!(to_lines(frames), to_lines(input.lines));
+    }
+
+    #[tokio::test]
+    async fn should_stream_the_frames_of_a_file() {
+        let lines = [HEADER, &game(0), "not json", &game(10)];
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, lines.join("\n").as_bytes()).unwrap();
+
+        let async_input = AsyncInput::from_path(file.path().to_path_buf(), ParseOptions::default())
+            .await
+            .unwrap();
+        let frames: Vec<Frame> = async_input.lines.collect().await;
+        assert_eq!(
+            to_lines(frames),
+            to_lines(input(&lines, ParseOptions::default()).lines)
+        );
+        assert_eq!(async_input.stats.skipped_lines(), 1);
+    }
+
+    #[test]
+    fn should_parse_the_headers_in_the_middle_of_the_stream() {
+        let header = r#"{"frameDuration":50,"size":{"width":20,"height":15},"grid":"hex","featuresWithVersion":{},"metadatas":{}}"#;
+        match Frame::parse(header).unwrap() {
+            Frame::Options(options) => {
+                assert_eq!(options.frame_duration, 50);
+                assert_eq!(options.size.width, 20);
+                assert_eq!(options.grid, Grid::Hex);
+            }
+            Frame::Game(_) => panic!("the header was parsed as a game"),
+        }
+        assert!(matches!(Frame::parse(&game(10)).unwrap(), Frame::Game(game) if game.score == 10));
+        // the error reported is the one of the game
+        let error = Frame::parse(r#"{"snake":{}}"#).unwrap_err().to_string();
+        assert!(error.contains("direction"), "{}", error);
+
+        let input = input(
+            &[HEADER, &game(0), header, &game(0)],
+            ParseOptions::default(),
+        );
+        assert_eq!(input.options.frame_duration, 100);
+        assert_eq!(scores(input.lines), vec![Some(0), None, Some(0)]);
+    }
+
+    #[test]
+    fn should_pass_the_unknown_fields_through() {
+        let header = r#"{"frameDuration":100,"size":{"width":10,"height":10},"featuresWithVersion":{},"metadatas":{},"theme":{"dark":true},"version":3}"#;
+        let game = r#"{"snake":{"direction":"right","head":{"x":1,"y":1},"tail":[]},"fruit":{"x":9,"y":9},"score":0,"state":"running","combo":[1,2],"player":"p1"}"#;
+        for line in [header, game] {
+            let frame = Frame::parse(line).unwrap();
+            let expected: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(serde_json::to_value(&frame).unwrap(), expected);
+        }
+
+        let input = input(&[header, game], ParseOptions::default());
+        assert_eq!(input.options.extra["version"], 3);
+        assert_eq!(input.options.extra["theme"]["dark"], true);
+        match input.lines.last() {
+            Some(Frame::Game(parsed)) => {
+                assert_eq!(parsed.extra["player"], "p1");
+                assert_eq!(
+                    serde_json::to_value(&parsed).unwrap(),
+                    serde_json::from_str::<serde_json::Value>(game).unwrap()
+                );
+            }
+            _ => panic!("the game is missing"),
+        }
+    }
 }
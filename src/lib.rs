@@ -1,17 +1,30 @@
 #[doc(hidden)]
 pub mod cli;
+pub mod codec;
 pub mod common;
 #[doc(hidden)]
+pub mod edit;
+#[doc(hidden)]
+pub mod export;
+#[doc(hidden)]
 pub mod gamestate;
+#[doc(hidden)]
+pub mod heatmap;
 pub mod input;
 #[doc(hidden)]
 pub mod net;
 #[doc(hidden)]
 pub mod pipeline;
+pub mod recording;
 #[doc(hidden)]
 pub mod render;
 #[doc(hidden)]
 pub mod render_browser;
+pub mod schema;
+#[doc(hidden)]
+pub mod snapshot;
+#[doc(hidden)]
+pub mod stats;
 #[doc(hidden)]
 pub mod stream_sse;
 #[doc(hidden)]
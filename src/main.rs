@@ -1,20 +1,33 @@
 use clap::{CommandFactory, Parser};
 use clap_complete::{generate, Shell};
 use crossterm;
+use std::io::Write;
+use std::path::Path;
 
 use snakepipe::cli::{AvailableShells, Cli, CliOptions, Commands};
 
+use snakepipe::codec::{run_decode as decode_run, run_encode as encode_run};
+use snakepipe::edit::{run as edit_run, EditOptions};
+use snakepipe::export::{run as export_run, ExportOptions};
+use snakepipe::gamestate::ghost::Ghost;
+use snakepipe::gamestate::map::Map;
 use snakepipe::gamestate::run as gamestate_run;
-use snakepipe::input::InitOptions;
+use snakepipe::heatmap::{run as heatmap_run, HeatmapOptions};
+use snakepipe::input::{InitOptions, ParseOptions};
 use snakepipe::net::common::StreamType;
 use snakepipe::net::play::block_on_play;
 use snakepipe::net::watch::block_on_watch;
 use snakepipe::pipeline::generate_command as pipeline_generate_command;
+use snakepipe::recording::{create_output, Compression};
 use snakepipe::render::run as render_run;
 use snakepipe::render_browser::common::port_is_available;
 use snakepipe::render_browser::run as render_browser_run;
+use snakepipe::schema::{run_schema as schema_run, run_validate as validate_run};
+use snakepipe::snapshot::{run as snapshot_run, SnapshotOptions};
+use snakepipe::stats::run as stats_run;
 use snakepipe::stream_sse::run as stream_sse_run;
-use snakepipe::throttle::run as throttle_run;
+use snakepipe::throttle::interactive::run as throttle_interactive_run;
+use snakepipe::throttle::{run as throttle_run, Playback};
 use snakepipe::utils::resolve_path;
 
 fn generate_completion(shell: Shell) {
@@ -26,8 +39,26 @@ fn generate_completion(shell: Shell) {
     )
 }
 
+/// Opens the file to write to (`--output`, compressed according to `compress` or its extension), exits if it
+/// can't be created - `None` without `--output`
+fn create_output_or_exit(
+    output: &Option<String>,
+    compress: Option<Compression>,
+) -> Option<Box<dyn Write>> {
+    output
+        .as_ref()
+        .map(|path| match create_output(Path::new(path), compress) {
+            Ok(recording) => recording,
+            Err(e) => {
+                eprintln!("Error occurred while creating {}: \"{}\"", path, e);
+                std::process::exit(exitcode::CANTCREAT);
+            }
+        })
+}
+
 fn main() {
     let cli = Cli::parse();
+    let parse_options = ParseOptions { strict: cli.strict };
 
     match &cli.command {
         Commands::Gamestate {
@@ -36,6 +67,16 @@ fn main() {
             height,
             snake_length,
             fit_terminal,
+            shrink_every,
+            portals,
+            map,
+            rivals,
+            power_ups,
+            hex,
+            seed,
+            ghost,
+            output,
+            compress,
         } => {
             let cli_options = CliOptions {
                 frame_duration: frame_duration,
@@ -43,32 +84,210 @@ fn main() {
                 height: height,
                 snake_length: snake_length,
                 fit_terminal: fit_terminal,
+                shrink_every,
+                portals,
+                rivals,
+                power_ups,
+                hex,
+                seed,
+            };
+            let mut game_options: InitOptions = cli_options.into();
+            let ghost = match ghost {
+                Some(path) => match Ghost::from_recording(Path::new(path), parse_options) {
+                    Ok((ghost_options, ghost)) => {
+                        // same board and options as the run of the ghost (and its seed, unless another one is given)
+                        game_options.size = ghost_options.size;
+                        game_options.grid = ghost_options.grid;
+                        game_options.seed = game_options.seed.or(ghost_options.seed);
+                        // not recorded by older versions
+                        if ghost_options.snake_length > 0 {
+                            game_options.snake_length = ghost_options.snake_length;
+                        }
+                        game_options.shrink_every = ghost_options.shrink_every;
+                        game_options.portals = ghost_options.portals;
+                        game_options.map_portals = ghost_options.map_portals;
+                        game_options.rivals = ghost_options.rivals;
+                        game_options.power_ups = ghost_options.power_ups;
+                        for key in ["shrink-every", "rivals"] {
+                            match ghost_options.metadatas.get(key) {
+                                Some(value) => game_options
+                                    .metadatas
+                                    .insert(key.to_string(), value.clone()),
+                                None => game_options.metadatas.remove(key),
+                            };
+                        }
+                        game_options
+                            .metadatas
+                            .insert("ghost".to_string(), path.clone());
+                        Some(ghost)
+                    }
+                    Err(e) => {
+                        eprintln!("Error occurred while loading ghost {}: \"{}\"", path, e);
+                        std::process::exit(exitcode::DATAERR);
+                    }
+                },
+                None => None,
             };
-            let game_options: InitOptions = cli_options.into();
+            // recorded, so that the board can be played again
+            let seed = game_options
+                .seed
+                .unwrap_or_else(|| rand::random::<u32>().into());
+            game_options.seed = Some(seed);
+            if let Some(path) = map {
+                match Map::from_file(std::path::Path::new(path)).and_then(|map| {
+                    map.check(game_options.size.width, game_options.size.height)?;
+                    Ok(map)
+                }) {
+                    Ok(map) => game_options.map_portals = map.portals,
+                    Err(e) => {
+                        eprintln!("Error occurred while loading map {}: \"{}\"", path, e);
+                        std::process::exit(exitcode::DATAERR);
+                    }
+                }
+            }
 
+            // opened first: exiting on error must not leave the terminal in raw mode
+            let recording = create_output_or_exit(output, *compress);
             // enable_raw_mode()?; // https://docs.rs/crossterm/0.27.0/crossterm/terminal/index.html#raw-mode
             let _ = crossterm::terminal::enable_raw_mode();
-            let _ = gamestate_run(game_options); // this function returns Ok(()) when ctrl+c is hit and Err when it couldn't write to stdout
+            let _ = gamestate_run(game_options, ghost, recording); // this function returns Ok(()) when ctrl+c is hit and Err when it couldn't write to stdout
             let _ = crossterm::terminal::disable_raw_mode();
             std::process::exit(130); // todo handle other signals ?
         }
-        Commands::Render => {
-            render_run();
+        Commands::Render { input } => {
+            render_run(input.as_deref().map(Path::new), parse_options);
         }
         Commands::Throttle {
             frame_duration,
+            max_pause,
             loop_infinite,
-        } => throttle_run(*frame_duration, *loop_infinite),
-        Commands::RenderBrowser { port } => {
+            speed,
+            interactive,
+            input,
+            output,
+            compress,
+        } => {
+            let playback = Playback {
+                frame_duration: *frame_duration,
+                max_pause: *max_pause,
+                speed: *speed,
+                loop_infinite: *loop_infinite,
+            };
+            let run = if *interactive {
+                throttle_interactive_run
+            } else {
+                throttle_run
+            };
+            run(
+                playback,
+                input.as_deref().map(Path::new),
+                create_output_or_exit(output, *compress),
+                parse_options,
+            )
+        }
+        Commands::Encode => encode_run(parse_options),
+        Commands::Decode => decode_run(parse_options),
+        Commands::Edit {
+            input,
+            from_tick,
+            to_tick,
+            drop_paused,
+            reverse,
+        } => {
+            let input_paths: Vec<&Path> = input.iter().map(Path::new).collect();
+            let edit_options = EditOptions {
+                from_tick: *from_tick,
+                to_tick: *to_tick,
+                drop_paused: *drop_paused,
+                reverse: *reverse,
+            };
+            edit_run(&input_paths, edit_options, parse_options)
+        }
+        Commands::Stats { recording, json } => {
+            stats_run(recording.as_deref().map(Path::new), *json, parse_options)
+        }
+        Commands::Heatmap {
+            recording,
+            format,
+            layer,
+            cell_size,
+            output,
+        } => {
+            let heatmap_options = HeatmapOptions {
+                format: *format,
+                layer: *layer,
+                cell_size: *cell_size,
+            };
+            let output = create_output_or_exit(output, None)
+                .unwrap_or_else(|| Box::new(std::io::stdout().lock()));
+            heatmap_run(
+                recording.as_deref().map(Path::new),
+                heatmap_options,
+                output,
+                parse_options,
+            )
+        }
+        Commands::Export {
+            recording,
+            format,
+            output,
+            speed,
+            from_tick,
+            to_tick,
+            cell_size,
+            events_only,
+        } => {
+            let export_options = ExportOptions {
+                format: *format,
+                speed: *speed,
+                from_tick: *from_tick,
+                to_tick: *to_tick,
+                cell_size: *cell_size,
+                events_only: *events_only,
+            };
+            let output = create_output_or_exit(output, None)
+                .unwrap_or_else(|| Box::new(std::io::stdout().lock()));
+            export_run(
+                recording.as_deref().map(Path::new),
+                export_options,
+                output,
+                parse_options,
+            )
+        }
+        Commands::Snapshot {
+            recording,
+            tick,
+            format,
+            cell_size,
+            output,
+        } => {
+            let snapshot_options = SnapshotOptions {
+                tick: *tick,
+                format: *format,
+                cell_size: *cell_size,
+            };
+            let output = create_output_or_exit(output, None)
+                .unwrap_or_else(|| Box::new(std::io::stdout().lock()));
+            snapshot_run(
+                recording.as_deref().map(Path::new),
+                snapshot_options,
+                output,
+                parse_options,
+            )
+        }
+        Commands::Schema => schema_run(),
+        Commands::Validate { input } => validate_run(input.as_deref().map(Path::new)),
+        Commands::RenderBrowser { port, input } => {
             if port_is_available(*port) {
-                return render_browser_run(*port);
+                return render_browser_run(*port, input.as_deref().map(Path::new), parse_options);
             }
             eprintln!("Error: port {} already in use", port);
             std::process::exit(exitcode::UNAVAILABLE);
         }
         Commands::StreamSse { address } => stream_sse_run(address.to_string()),
         #[cfg(unix)]
-        Commands::SocketPlay { path } => match resolve_path(std::path::PathBuf::from(&path)) {
+        Commands::SocketPlay { path, input } => match resolve_path(std::path::PathBuf::from(&path))
+        {
             Ok(path) => {
                 match std::fs::remove_file(&path) {
                     Ok(_) => {}
@@ -79,7 +298,11 @@ fn main() {
                         }
                     }
                 }
-                let _ = block_on_play(StreamType::Socket(path));
+                let _ = block_on_play(
+                    StreamType::Socket(path),
+                    input.as_deref().map(Path::new),
+                    parse_options,
+                );
             }
             Err(_) => {
                 eprintln!("{} not found.", path);
@@ -98,8 +321,12 @@ fn main() {
                 std::process::exit(exitcode::OSFILE);
             }
         },
-        Commands::TcpPlay { port, host } => {
-            let _ = block_on_play(StreamType::Tcp(format!("{}:{}", host, port).to_string()));
+        Commands::TcpPlay { port, host, input } => {
+            let _ = block_on_play(
+                StreamType::Tcp(format!("{}:{}", host, port).to_string()),
+                input.as_deref().map(Path::new),
+                parse_options,
+            );
         }
         Commands::TcpWatch { port, host } => {
             let _ = block_on_watch(StreamType::Tcp(format!("{}:{}", host, port).to_string()));
@@ -1,31 +1,38 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
 
 use crate::common::format_version_to_display;
-use crate::input::{parse_gamestate, Game, InitOptions};
+use crate::input::{parse_gamestate_async_from, Frame, InitOptions, ParseOptions};
 use crate::net::common::StreamType;
+use crate::recording::input_name;
+use futures_util::{Stream, StreamExt};
+use parking_lot::Mutex;
 use tokio::io::AsyncWriteExt;
 use tokio::net::{TcpListener, UnixListener};
 use tokio::runtime::Runtime;
 use tokio::sync::broadcast;
 
-pub fn block_on_play(props: StreamType) -> std::io::Result<()> {
+pub fn block_on_play(
+    props: StreamType,
+    input_path: Option<&Path>,
+    parse_options: ParseOptions,
+) -> std::io::Result<()> {
     let rt = Runtime::new()?;
-    rt.block_on(play(props));
+    rt.block_on(play(props, input_path, parse_options));
     Ok(())
 }
 
-pub async fn play(props: StreamType) {
-    match parse_gamestate() {
+pub async fn play(props: StreamType, input_path: Option<&Path>, parse_options: ParseOptions) {
+    match parse_gamestate_async_from(input_path, parse_options).await {
         Ok(input) => {
-            // prepare init_options
-            let mut options_passthrough = input.options.clone();
-            options_passthrough
-                .features_with_version
-                .insert("tcp-play-rust".to_string(), format_version_to_display());
+            // prepare init_options (shared: replaced when a new session starts, sent to the clients when they connect)
+            let options_passthrough = passthrough_options(&input.options);
             println!("{}\r", serde_json::to_string(&options_passthrough).unwrap());
+            let options_passthrough = Arc::new(Mutex::new(options_passthrough));
 
             // prepare broadcast channel that will link between stdin and writing into tcp/unix sockets
-            let (tx, _) = broadcast::channel::<Game>(10);
+            let (tx, _) = broadcast::channel::<Frame>(10);
 
             // according to the input passed (either address:port, or filepath to unix socket)
             // spawn the correct server (tcp or unix socket)
@@ -47,20 +54,44 @@ pub async fn play(props: StreamType) {
             }
 
             // reading stdin and broadcast to tx that will trigger writes on opened tcp/unix sockets
-            brodcast_lines(input.lines, tx.clone()).unwrap();
+            brodcast_lines(input.lines, tx.clone(), options_passthrough)
+                .await
+                .unwrap();
         }
         Err(e) => {
-            eprintln!("Error occurred while parsing stdin: \"{}\"", e);
+            eprintln!(
+                "Error occurred while parsing {}: \"{}\"",
+                input_name(input_path),
+                e
+            );
             std::process::exit(exitcode::DATAERR);
         }
     }
 }
 
-fn brodcast_lines(
-    lines: Box<dyn Iterator<Item = Game>>,
-    tx: broadcast::Sender<Game>,
+/// The header to output (and send to the clients) for a session: `tcp-play-rust` added to the features
+fn passthrough_options(options: &InitOptions) -> InitOptions {
+    let mut options_passthrough = options.clone();
+    options_passthrough
+        .features_with_version
+        .insert("tcp-play-rust".to_string(), format_version_to_display());
+    options_passthrough
+}
+
+async fn brodcast_lines(
+    mut lines: Pin<Box<dyn Stream<Item = Frame> + Send>>,
+    tx: broadcast::Sender<Frame>,
+    init_options: Arc<Mutex<InitOptions>>,
 ) -> std::io::Result<()> {
-    for parsed_line in lines {
+    while let Some(parsed_line) = lines.next().await {
+        let parsed_line = match parsed_line {
+            Frame::Options(options) => {
+                let options = passthrough_options(&options);
+                *init_options.lock() = options.clone();
+                Frame::Options(options)
+            }
+            frame => frame,
+        };
         println!("{}\r", serde_json::to_string(&parsed_line).unwrap());
         // we don't care if there are clients to broadcast yet or not
         let _ = tx.send(parsed_line);
@@ -70,8 +101,8 @@ fn brodcast_lines(
 
 async fn create_tcp_server(
     bind_addr: String,
-    tx: tokio::sync::broadcast::Sender<Game>,
-    init_options: InitOptions,
+    tx: tokio::sync::broadcast::Sender<Frame>,
+    init_options: Arc<Mutex<InitOptions>>,
 ) -> std::io::Result<()> {
     match TcpListener::bind(&bind_addr).await {
         Ok(listener) => loop {
@@ -100,8 +131,8 @@ async fn create_tcp_server(
 
 async fn create_socket_server(
     socket_path: PathBuf,
-    tx: tokio::sync::broadcast::Sender<Game>,
-    init_options: InitOptions,
+    tx: tokio::sync::broadcast::Sender<Frame>,
+    init_options: Arc<Mutex<InitOptions>>,
 ) -> std::io::Result<()> {
     let listener = UnixListener::bind(socket_path)?;
     loop {
@@ -120,12 +151,14 @@ async fn create_socket_server(
 
 async fn handle_client_task(
     mut stream: impl AsyncWriteExt + std::marker::Unpin,
-    tx: tokio::sync::broadcast::Sender<Game>,
-    init_options: InitOptions,
+    tx: tokio::sync::broadcast::Sender<Frame>,
+    init_options: Arc<Mutex<InitOptions>>,
 ) -> std::io::Result<()> {
     let mut rx = tx.subscribe();
+    // options of the current session (the lock must not be held across the write)
+    let init_options = serde_json::to_string(&*init_options.lock()).unwrap();
     stream
-        .write_all(format!("{}\r\n", serde_json::to_string(&init_options).unwrap()).as_bytes())
+        .write_all(format!("{}\r\n", init_options).as_bytes())
         .await
         .unwrap();
     loop {
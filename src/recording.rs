@@ -0,0 +1,222 @@
+//! Reading and writing recordings: stdin/stdout or files, compressed with gzip or zstd
+
+use std::fs::File;
+use std::io::{stdin, BufRead, BufReader, Read, Result, Write};
+use std::path::Path;
+
+use clap::ValueEnum;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use ruzstd::decoding::StreamingDecoder;
+use ruzstd::encoding::{compress, CompressionLevel};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Above this size, the data buffered by the zstd writer is compressed into a frame
+const ZSTD_FRAME_SIZE: usize = 128 * 1024;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// From the extension of the file (`.gz` or `.zst`)
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("gz") => Some(Self::Gzip),
+            Some("zst") => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// From the first bytes of a stream (the extension of a file can't be trusted, stdin has none)
+    fn detect(buffer: &[u8]) -> Option<Self> {
+        if buffer.starts_with(GZIP_MAGIC) {
+            Some(Self::Gzip)
+        } else if buffer.starts_with(ZSTD_MAGIC) {
+            Some(Self::Zstd)
+        } else {
+            None
+        }
+    }
+}
+
+/// How to refer to the input in messages
+pub fn input_name(path: Option<&Path>) -> String {
+    match path {
+        Some(path) => path.display().to_string(),
+        None => "stdin".to_string(),
+    }
+}
+
+/// Opens the file at `path` (or stdin when `None`), decompressing it if it was compressed with gzip or zstd
+pub fn open_input(path: Option<&Path>) -> Result<Box<dyn BufRead>> {
+    match path {
+        Some(path) => decompress(BufReader::new(File::open(path)?)),
+        None => decompress(stdin().lock()),
+    }
+}
+
+fn decompress<T: BufRead + 'static>(mut reader: T) -> Result<Box<dyn BufRead>> {
+    Ok(match Compression::detect(reader.fill_buf()?) {
+        // recordings can be concatenated (`cat a.gz b.gz`)
+        Some(Compression::Gzip) => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Some(Compression::Zstd) => Box::new(BufReader::new(ZstdReader {
+            decoder: None,
+            source: Some(reader),
+        })),
+        None => Box::new(reader),
+    })
+}
+
+/// Creates the file at `path`, compressed according to `compression` or its extension
+///
+/// The data is only guaranteed to be written once the writer is dropped.
+pub fn create_output(path: &Path, compression: Option<Compression>) -> Result<Box<dyn Write>> {
+    let file = File::create(path)?;
+    Ok(match compression.or_else(|| Compression::from_path(path)) {
+        Some(Compression::Gzip) => Box::new(GzEncoder::new(file, flate2::Compression::default())),
+        Some(Compression::Zstd) => Box::new(ZstdWriter {
+            buffer: Vec::new(),
+            target: file,
+        }),
+        None => Box::new(file),
+    })
+}
+
+/// Decodes the zstd frames one after the other (a [`ZstdWriter`] writes several of them)
+struct ZstdReader<R: BufRead> {
+    decoder: Option<StreamingDecoder<R, ruzstd::decoding::FrameDecoder>>,
+    /// the reader, when between two frames
+    source: Option<R>,
+}
+
+impl<R: BufRead> Read for ZstdReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        loop {
+            if let Some(decoder) = self.decoder.as_mut() {
+                let read = decoder.read(buf)?;
+                if read > 0 || buf.is_empty() {
+                    return Ok(read);
+                }
+                self.source = self.decoder.take().map(|decoder| decoder.into_inner());
+            }
+            match self.source.take() {
+                Some(mut source) => {
+                    if source.fill_buf()?.is_empty() {
+                        return Ok(0);
+                    }
+                    let decoder = StreamingDecoder::new(source).map_err(|e| {
+                        std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+                    })?;
+                    self.decoder = Some(decoder);
+                }
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Buffers the data and compresses it into zstd frames (on flush, drop or when the buffer is big enough)
+struct ZstdWriter<W: Write> {
+    buffer: Vec<u8>,
+    target: W,
+}
+
+impl<W: Write> Write for ZstdWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= ZSTD_FRAME_SIZE {
+            self.flush()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if !self.buffer.is_empty() {
+            let mut frame = Vec::new();
+            compress(
+                self.buffer.as_slice(),
+                &mut frame,
+                CompressionLevel::Fastest,
+            );
+            self.target.write_all(&frame)?;
+            self.buffer.clear();
+        }
+        self.target.flush()
+    }
+}
+
+impl<W: Write> Drop for ZstdWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines of NDJSON, `count` of them
+    fn content(count: usize) -> String {
+        (0..count)
+            .map(|index| format!("{{\"score\":{}}}\n", index))
+            .collect()
+    }
+
+    fn write(path: &Path, compression: Option<Compression>, content: &str) {
+        let mut output = create_output(path, compression).unwrap();
+        output.write_all(content.as_bytes()).unwrap();
+    }
+
+    fn read(path: &Path) -> String {
+        let mut content = String::new();
+        open_input(Some(path))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn should_read_back_what_was_written() {
+        let dir = tempfile::tempdir().unwrap();
+        // several zstd frames
+        let content = content(20_000);
+        assert!(content.len() > 2 * ZSTD_FRAME_SIZE);
+        for (name, compression, magic) in [
+            ("plain.ndjson", None, &b"{"[..]),
+            ("compressed.ndjson.gz", None, GZIP_MAGIC),
+            ("compressed.ndjson.zst", None, ZSTD_MAGIC),
+            ("forced.ndjson", Some(Compression::Gzip), GZIP_MAGIC),
+            ("forced.ndjson", Some(Compression::Zstd), ZSTD_MAGIC),
+        ] {
+            let path = dir.path().join(name);
+            write(&path, compression, &content);
+            assert!(std::fs::read(&path).unwrap().starts_with(magic), "{}", name);
+            assert_eq!(read(&path), content, "{}", name);
+        }
+    }
+
+    #[test]
+    fn should_read_the_concatenated_gzip_recordings() {
+        let dir = tempfile::tempdir().unwrap();
+        let (first, second) = (dir.path().join("a.gz"), dir.path().join("b.gz"));
+        write(&first, None, &content(3));
+        write(&second, None, "{\"score\":10}\n");
+        let concatenated = dir.path().join("ab.gz");
+        std::fs::write(
+            &concatenated,
+            [
+                std::fs::read(&first).unwrap(),
+                std::fs::read(&second).unwrap(),
+            ]
+            .concat(),
+        )
+        .unwrap();
+        assert_eq!(read(&concatenated), content(3) + "{\"score\":10}\n");
+    }
+}
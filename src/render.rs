@@ -1,37 +1,61 @@
 use ctrlc;
 use std::io::Write;
+use std::path::Path;
 
 use crate::common::{format_metadatas, format_version};
-use crate::input::{parse_gamestate, Game, GameState};
+use crate::input::{
+    parse_gamestate_from, Effect, Frame, Game, Grid, InitOptions, ParseOptions, Position,
+    PowerUpKind,
+};
+use crate::recording::input_name;
 use array2d::Array2D;
 use crossterm::{cursor, queue, style, terminal};
 
 #[derive(Clone, Debug)]
-enum Point {
+pub(crate) enum Point {
     Head,
     Tail,
+    RivalHead,
+    RivalTail,
+    /// Snake of a previous run (`gamestate --ghost`)
+    GhostHead,
+    GhostTail,
     Fruit,
+    Portal,
+    PowerUp(PowerUpKind),
+    Wall,
+    /// Shade of a heatmap, from 1 (lightest) to 4
+    Heat(u8),
     Nothing,
 }
 
 #[derive(Debug)]
-struct RenderGrid {
-    data: Array2D<Point>,
+pub(crate) struct RenderGrid {
+    pub(crate) data: Array2D<Point>,
+    pub(crate) grid: Grid,
 }
 
 impl RenderGrid {
-    fn new(width: u32, height: u32) -> Self {
+    pub(crate) fn new(width: u32, height: u32, grid: Grid) -> Self {
         RenderGrid {
             data: Array2D::filled_with(Point::Nothing, height as usize, width as usize),
+            grid,
         }
     }
-    fn set(&mut self, x: usize, y: usize, point: Point) {
+    pub(crate) fn set(&mut self, x: usize, y: usize, point: Point) {
         let _ = self.data.set(y, x, point);
     }
+    /// Sets the cell where `position` is drawn (ignoring what's outside of the board)
+    pub(crate) fn place(&mut self, position: &Position, point: Point) {
+        let (x, y) = self.grid.to_offset(position);
+        if x >= 0 && y >= 0 {
+            self.set(x as usize, y as usize, point);
+        }
+    }
 }
 
-pub fn run() {
-    match parse_gamestate() {
+pub fn run(input_path: Option<&Path>, parse_options: ParseOptions) {
+    match parse_gamestate_from(input_path, parse_options) {
         Ok(input) => {
             ctrlc::set_handler(|| {
                 // cleanup on ctrl+c
@@ -46,37 +70,28 @@ pub fn run() {
             })
             .expect("Could not send signal on channel.");
 
-            let version = format_version(input.options.features_with_version);
-            let formatted_metadatas = format_metadatas(
-                input.options.metadatas,
-                input.options.frame_duration,
-                input.options.size,
-            );
-            let formatted_metadatas = if formatted_metadatas.is_empty() {
-                "".to_string()
-            } else {
-                format!(" - {}", formatted_metadatas)
-            };
-
             let mut stdout = std::io::stdout();
-            queue!(
-                stdout,
-                terminal::Clear(terminal::ClearType::All),
-                cursor::Hide,
-                cursor::MoveTo(0, 0),
-                cursor::SavePosition,
-            )
-            .unwrap();
-            for parsed_line in input.lines {
-                let mut grid = RenderGrid::new(input.options.size.width, input.options.size.height);
+            let mut options = input.options;
+            let (mut version, mut formatted_metadatas) = prepare_session(&options, &mut stdout);
+            for frame in input.lines {
+                let parsed_line = match frame {
+                    Frame::Game(parsed_line) => parsed_line,
+                    Frame::Options(new_options) => {
+                        // a new session started, the board may not have the same size
+                        options = new_options;
+                        (version, formatted_metadatas) = prepare_session(&options, &mut stdout);
+                        continue;
+                    }
+                };
+                let mut grid =
+                    RenderGrid::new(options.size.width, options.size.height, options.grid);
                 prepare_grid(&mut grid, parsed_line.clone());
                 render_frame(
                     &grid,
                     &version,
                     &formatted_metadatas,
-                    input.options.size.width,
-                    parsed_line.score,
-                    parsed_line.state,
+                    options.size.width,
+                    &parsed_line,
                     &mut stdout,
                 );
                 stdout.flush().unwrap();
@@ -91,26 +106,84 @@ pub fn run() {
             .unwrap();
         }
         Err(e) => {
-            eprintln!("Error occurred while parsing stdin: \"{}\"", e);
+            eprintln!(
+                "Error occurred while parsing {}: \"{}\"",
+                input_name(input_path),
+                e
+            );
             std::process::exit(exitcode::DATAERR);
         }
     }
 }
 
-fn prepare_grid(grid: &mut RenderGrid, game_state: Game) {
-    grid.set(
-        game_state.snake.head.x as usize,
-        game_state.snake.head.y as usize,
-        Point::Head,
+/// Clears the screen and returns the version and metadatas to display for the session described by `options`
+pub(crate) fn prepare_session<W: Write>(options: &InitOptions, stdout: &mut W) -> (String, String) {
+    let version = format_version(options.features_with_version.clone());
+    let formatted_metadatas = format_metadatas(
+        options.metadatas.clone(),
+        options.frame_duration,
+        options.size,
     );
+    let formatted_metadatas = if formatted_metadatas.is_empty() {
+        "".to_string()
+    } else {
+        format!(" - {}", formatted_metadatas)
+    };
+    queue!(
+        stdout,
+        terminal::Clear(terminal::ClearType::All),
+        cursor::Hide,
+        cursor::MoveTo(0, 0),
+        cursor::SavePosition,
+    )
+    .unwrap();
+    (version, formatted_metadatas)
+}
+
+pub(crate) fn prepare_grid(grid: &mut RenderGrid, game_state: Game) {
+    if let Some(bounds) = game_state.bounds {
+        for y in 0..grid.data.num_rows() {
+            for x in 0..grid.data.num_columns() {
+                if !bounds.contains(x as i32, y as i32) {
+                    grid.set(x, y, Point::Wall);
+                }
+            }
+        }
+    }
+    // under everything else, nothing collides with it
+    if let Some(ghost) = game_state.ghost {
+        ghost.tail.into_iter().for_each(|f| {
+            grid.place(&f, Point::GhostTail);
+        });
+        grid.place(&ghost.head, Point::GhostHead);
+    }
+    // under the snakes, which would otherwise vanish when passing through
+    game_state.portals.into_iter().for_each(|portal| {
+        grid.place(&portal.0, Point::Portal);
+        grid.place(&portal.1, Point::Portal);
+    });
+    grid.place(&game_state.snake.head, Point::Head);
     game_state.snake.tail.into_iter().for_each(|f| {
-        grid.set(f.x as usize, f.y as usize, Point::Tail);
+        grid.place(&f, Point::Tail);
     });
-    grid.set(
-        game_state.fruit.x as usize,
-        game_state.fruit.y as usize,
-        Point::Fruit,
-    );
+    game_state.rivals.into_iter().for_each(|rival| {
+        rival.snake.tail.into_iter().for_each(|f| {
+            grid.place(&f, Point::RivalTail);
+        });
+        grid.place(&rival.snake.head, Point::RivalHead);
+    });
+    if let Some(power_up) = game_state.power_up {
+        grid.place(&power_up.position, Point::PowerUp(power_up.kind));
+    }
+    grid.place(&game_state.fruit, Point::Fruit);
+}
+
+/// Active power-ups with the number of ticks left, to display next to the score
+pub(crate) fn format_effects(effects: &[Effect]) -> String {
+    effects
+        .iter()
+        .map(|effect| format!(" - {} ({})", effect.kind, effect.remaining))
+        .collect()
 }
 
 /**
@@ -126,46 +199,75 @@ fn render_line_wrapper(width: u32, top: bool) -> String {
     }
 }
 
-fn render_frame(
+/// Lines of the board, including its borders (`styled`: the ghost is dimmed with escape sequences)
+pub(crate) fn render_board(grid: &RenderGrid, width: u32, styled: bool) -> Vec<String> {
+    // hex cells are separated by a space, odd rows are shifted by half a cell
+    let width = match grid.grid {
+        Grid::Square => width,
+        Grid::Hex => width * 2,
+    };
+    let mut lines = vec![render_line_wrapper(width, true)];
+    grid.data
+        .rows_iter()
+        .enumerate()
+        .for_each(|(row_index, row)| {
+            let row_reduced: String = row.into_iter().fold("".to_string(), |row_acc, cell| {
+                let cell_content = match cell {
+                    Point::Fruit => "F",
+                    Point::Head => "H",
+                    Point::Portal => "O",
+                    Point::PowerUp(PowerUpKind::SlowMotion) => "S",
+                    Point::PowerUp(PowerUpKind::Ghost) => "G",
+                    Point::PowerUp(PowerUpKind::Magnet) => "M",
+                    Point::Nothing => "·",
+                    Point::Wall => "░",
+                    Point::Tail => "T",
+                    Point::RivalHead => "R",
+                    Point::RivalTail => "r",
+                    Point::GhostHead if styled => "\x1b[2mh\x1b[22m",
+                    Point::GhostTail if styled => "\x1b[2mt\x1b[22m",
+                    Point::GhostHead => "h",
+                    Point::GhostTail => "t",
+                    Point::Heat(1) => "░",
+                    Point::Heat(2) => "▒",
+                    Point::Heat(3) => "▓",
+                    Point::Heat(_) => "█",
+                };
+                match grid.grid {
+                    Grid::Square => format!("{}{}", row_acc, cell_content),
+                    Grid::Hex => format!("{}{} ", row_acc, cell_content),
+                }
+            });
+            let row_reduced = match grid.grid {
+                Grid::Hex if row_index % 2 == 1 => format!(" {}", row_reduced.trim_end()),
+                _ => row_reduced,
+            };
+            lines.push(format!("│{}│", row_reduced));
+        });
+    lines.push(render_line_wrapper(width, false));
+    lines
+}
+
+pub(crate) fn render_frame<W: Write>(
     grid: &RenderGrid,
     version: &String,
     formatted_metadatas: &String,
     width: u32,
-    score: u32,
-    state: GameState,
-    stdout: &mut std::io::Stdout,
+    game_state: &Game,
+    stdout: &mut W,
 ) {
+    queue!(stdout, cursor::RestorePosition).unwrap();
+    for line in render_board(grid, width, true) {
+        queue!(stdout, style::Print(line), cursor::MoveToNextLine(1)).unwrap();
+    }
     queue!(
         stdout,
-        cursor::RestorePosition,
-        style::Print(render_line_wrapper(width, true)),
-        cursor::MoveToNextLine(1)
-    )
-    .unwrap();
-    grid.data.rows_iter().for_each(|row| {
-        let row_reduced: String = row.into_iter().fold("".to_string(), |row_acc, cell| {
-            let cell_content = match cell {
-                Point::Fruit => "F",
-                Point::Head => "H",
-                Point::Nothing => "·",
-                Point::Tail => "T",
-            };
-            format!("{}{}", row_acc, cell_content)
-        });
-        queue!(
-            stdout,
-            style::Print(format!("│{}│", row_reduced)),
-            cursor::MoveToNextLine(1)
-        )
-        .unwrap();
-    });
-    queue!(
-        stdout,
-        style::Print(render_line_wrapper(width, false)),
-        cursor::MoveToNextLine(1),
         style::Print(format!(
-            "Score: {} - {}{}     ",
-            score, state, formatted_metadatas
+            "Score: {} - {}{}{}     ",
+            game_state.score,
+            game_state.state,
+            format_effects(&game_state.effects),
+            formatted_metadatas
         )),
         cursor::MoveToNextLine(1),
         style::Print(format!("[P] Pause [R] Restart [Ctrl+C] Quit")),
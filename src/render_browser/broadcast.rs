@@ -86,4 +86,17 @@ impl Broadcaster {
         // disconnected clients will get swept up by `remove_stale_clients`
         let _ = future::join_all(send_futures).await;
     }
+
+    /// Broadcasts `msg` to all clients as a named event (`event` field of the server-sent event).
+    pub async fn broadcast_event(&self, event: &str, msg: &str) {
+        let clients = self.inner.lock().clients.clone();
+
+        let send_futures = clients
+            .iter()
+            .map(|client| client.send(sse::Data::new(msg).event(event).into()));
+
+        // try to send to all clients, ignoring failures
+        // disconnected clients will get swept up by `remove_stale_clients`
+        let _ = future::join_all(send_futures).await;
+    }
 }
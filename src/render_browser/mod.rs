@@ -4,3 +4,5 @@ mod render;
 mod server;
 
 pub use crate::render_browser::render::run;
+/// The files of `static/`, embedded at build time
+pub(crate) use crate::render_browser::server::generate;
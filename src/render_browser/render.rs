@@ -1,25 +1,46 @@
+use futures_util::StreamExt;
+use std::path::Path;
+
 use crate::common::format_version_to_display;
-use crate::input::parse_gamestate;
+use crate::input::{parse_gamestate_async_from, Frame, InitOptions, ParseOptions};
+use crate::recording::input_name;
 use crate::render_browser::common::UrlToDisplay;
 use crate::render_browser::server::launch_server;
 
-pub fn run(port: u16) {
-    match parse_gamestate() {
+/// The header to output (and serve) for a session: `render-browser` added to the features, along with its url
+fn passthrough_options(options: &InitOptions, url_to_display: &str) -> InitOptions {
+    let mut options_passthrough = options.clone();
+    options_passthrough
+        .features_with_version
+        .insert("render-browser".to_string(), format_version_to_display());
+    options_passthrough.metadatas.insert(
+        "render-browser-host".to_string(),
+        url_to_display.to_string(),
+    );
+    options_passthrough
+}
+
+#[actix_web::main]
+pub async fn run(port: u16, input_path: Option<&Path>, parse_options: ParseOptions) {
+    match parse_gamestate_async_from(input_path, parse_options).await {
         Ok(input) => {
-            let mut options_passthrough = input.options.clone();
-            options_passthrough
-                .features_with_version
-                .insert("render-browser".to_string(), format_version_to_display());
-            let url_to_display = UrlToDisplay::new(port);
-            options_passthrough.metadatas.insert(
-                "render-browser-host".to_string(),
-                format!("{}", url_to_display.url).to_string(),
-            );
+            let url_to_display = UrlToDisplay::new(port).url;
+            let options_passthrough = passthrough_options(&input.options, &url_to_display);
             println!("{}\r", serde_json::to_string(&options_passthrough).unwrap());
-            let _ = launch_server(input.lines, options_passthrough, port);
+            let lines = input.lines.map(move |frame| match frame {
+                Frame::Options(options) => {
+                    Frame::Options(passthrough_options(&options, &url_to_display))
+                }
+                frame => frame,
+            });
+            let _ = launch_server(Box::pin(lines), options_passthrough, port).await;
         }
         Err(e) => {
-            eprintln!("Error occurred while parsing stdin: \"{}\"", e);
+            eprintln!(
+                "Error occurred while parsing {}: \"{}\"",
+                input_name(input_path),
+                e
+            );
             std::process::exit(exitcode::DATAERR);
         }
     }
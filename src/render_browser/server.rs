@@ -1,21 +1,36 @@
+use std::pin::Pin;
 use std::sync::Arc;
 
 use actix_web::{get, web, App, HttpResponse, HttpServer, Responder};
 use actix_web_static_files::ResourceFiles;
+use futures_util::{Stream, StreamExt};
+use parking_lot::Mutex;
 
-use crate::input::{Game, InitOptions};
+use crate::input::{Frame, InitOptions};
 use crate::render_browser::broadcast::Broadcaster;
 
 include!(concat!(env!("OUT_DIR"), "/generated.rs"));
 
 async fn do_broadcast_task(
     broadcaster: Arc<Broadcaster>,
-    mut lines: Box<dyn Iterator<Item = Game>>,
+    init_options: Arc<Mutex<InitOptions>>,
+    mut lines: Pin<Box<dyn Stream<Item = Frame> + Send>>,
 ) {
-    while let Some(line) = lines.next() {
-        let msg = serde_json::to_string(&line).unwrap();
-        println!("{}\r", &msg);
-        broadcaster.broadcast(&msg).await;
+    while let Some(frame) = lines.next().await {
+        match frame {
+            Frame::Game(line) => {
+                let msg = serde_json::to_string(&line).unwrap();
+                println!("{}\r", &msg);
+                broadcaster.broadcast(&msg).await;
+            }
+            Frame::Options(options) => {
+                // a new session started: new clients will fetch these options, connected ones are notified
+                let msg = serde_json::to_string(&options).unwrap();
+                println!("{}\r", &msg);
+                *init_options.lock() = options;
+                broadcaster.broadcast_event("options", &msg).await;
+            }
+        }
     }
     std::process::exit(0);
 }
@@ -26,19 +41,19 @@ async fn event_stream(broadcaster: web::Data<Broadcaster>) -> impl Responder {
 }
 
 #[get("/init-options")]
-async fn get_init_options(init_options: web::Data<InitOptions>) -> impl Responder {
-    HttpResponse::Ok().json(init_options)
+async fn get_init_options(init_options: web::Data<Mutex<InitOptions>>) -> impl Responder {
+    HttpResponse::Ok().json(&*init_options.lock())
 }
 
-#[actix_web::main]
 pub async fn launch_server(
-    lines: Box<dyn Iterator<Item = Game>>,
+    lines: Pin<Box<dyn Stream<Item = Frame> + Send>>,
     init_options: InitOptions,
     port: u16,
 ) -> std::io::Result<()> {
     let broadcaster = Broadcaster::create();
     let broadcaster_clone = broadcaster.clone();
-    let rc_init_options = Arc::new(init_options);
+    let rc_init_options = Arc::new(Mutex::new(init_options));
+    let rc_init_options_clone = rc_init_options.clone();
 
     let server = HttpServer::new(move || {
         let generated = generate();
@@ -54,7 +69,11 @@ pub async fn launch_server(
 
     let server_task = actix_web::rt::spawn(server);
 
-    let broadcast_task = actix_web::rt::spawn(do_broadcast_task(broadcaster_clone, lines));
+    let broadcast_task = actix_web::rt::spawn(do_broadcast_task(
+        broadcaster_clone,
+        rc_init_options_clone,
+        lines,
+    ));
 
     let _ = tokio::try_join!(server_task, broadcast_task).expect("Unable to join tasks");
 
@@ -0,0 +1,150 @@
+//! JSON Schema of the wire protocol (`snakepipe schema` / `snakepipe validate`)
+//!
+//! Generated from [`InitOptions`] and [`Game`], so it can't drift from what the Rust implementation reads and writes.
+//! The first line of a stream is an [`InitOptions`], the following ones are [`Frame`]s.
+
+use std::io::BufRead;
+use std::path::Path;
+
+use jsonschema::JSONSchema;
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+use serde_json::Value;
+
+use crate::codec;
+use crate::input::{Frame, Game, InitOptions};
+use crate::recording::{input_name, open_input};
+
+/// Schema of a line of the stream (header or game)
+pub fn frame_schema() -> RootSchema {
+    schema_for!(Frame)
+}
+
+/// Prints the schema of a line of the stream on stdout
+pub fn run_schema() {
+    println!("{}", serde_json::to_string_pretty(&frame_schema()).unwrap());
+}
+
+fn compile(schema: RootSchema) -> JSONSchema {
+    JSONSchema::compile(&serde_json::to_value(schema).unwrap()).expect("generated schema is valid")
+}
+
+/// Violations of `value` against `schema`, as `path: message`
+fn violations(schema: &JSONSchema, value: &Value) -> Vec<String> {
+    match schema.validate(value) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .map(|error| format!("{}: {}", error.instance_path, error))
+            .collect(),
+    }
+}
+
+/// Violations of the line at `line_number` (starting at 1, the header)
+fn check_line(
+    options_schema: &JSONSchema,
+    game_schema: &JSONSchema,
+    line_number: usize,
+    line: std::io::Result<String>,
+) -> Vec<String> {
+    match line.map_err(|e| e.to_string()).and_then(|line| {
+        serde_json::from_str::<Value>(&line).map_err(|e| format!("invalid json: {}", e))
+    }) {
+        Ok(value) if line_number == 1 => violations(options_schema, &value),
+        Ok(value) => {
+            let game_errors = violations(game_schema, &value);
+            if !game_errors.is_empty() && violations(options_schema, &value).is_empty() {
+                Vec::new()
+            } else {
+                game_errors
+            }
+        }
+        Err(e) => vec![e],
+    }
+}
+
+/// Checks each line of the input against the schema, reporting the violations on stdout
+///
+/// Like when parsing, a line after the first one is checked as [`Game`], then as [`InitOptions`]
+/// (a new session) - the violations reported are the ones from [`Game`].
+/// Exits with an error if any line is invalid.
+pub fn run_validate(input_path: Option<&Path>) {
+    let mut reader = match open_input(input_path) {
+        Ok(reader) => reader,
+        Err(e) => {
+            eprintln!(
+                "Error occurred while parsing {}: \"{}\"",
+                input_name(input_path),
+                e
+            );
+            std::process::exit(exitcode::NOINPUT);
+        }
+    };
+    if reader.fill_buf().is_ok_and(codec::is_binary) {
+        eprintln!(
+            "Binary recordings can't be validated, convert them with `snakepipe decode` first"
+        );
+        std::process::exit(exitcode::DATAERR);
+    }
+    let options_schema = compile(schema_for!(InitOptions));
+    let game_schema = compile(schema_for!(Game));
+    let mut lines_read = 0;
+    let mut invalid_lines = 0;
+    for (index, line) in reader.lines().enumerate() {
+        let line_number = index + 1;
+        lines_read = line_number;
+        let errors = check_line(&options_schema, &game_schema, line_number, line);
+        if !errors.is_empty() {
+            invalid_lines += 1;
+            for error in errors {
+                println!("Line {}: {}", line_number, error);
+            }
+        }
+    }
+    println!(
+        "{} lines checked, {} invalid in {}",
+        lines_read,
+        invalid_lines,
+        input_name(input_path)
+    );
+    if invalid_lines > 0 || lines_read == 0 {
+        std::process::exit(exitcode::DATAERR);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = r#"{"frameDuration":100,"size":{"width":10,"height":10},"featuresWithVersion":{},"metadatas":{}}"#;
+    const GAME: &str = r#"{"snake":{"direction":"right","head":{"x":1,"y":1},"tail":[{"x":0,"y":1}]},"fruit":{"x":9,"y":9},"score":0,"state":"running"}"#;
+
+    fn check(line_number: usize, line: &str) -> Vec<String> {
+        check_line(
+            &compile(schema_for!(InitOptions)),
+            &compile(schema_for!(Game)),
+            line_number,
+            Ok(line.to_string()),
+        )
+    }
+
+    #[test]
+    fn should_accept_the_conforming_lines() {
+        assert_eq!(check(1, HEADER), Vec::<String>::new());
+        assert_eq!(check(2, GAME), Vec::<String>::new());
+        // a new session
+        assert_eq!(check(3, HEADER), Vec::<String>::new());
+    }
+
+    #[test]
+    fn should_report_the_non_conforming_lines() {
+        let errors = check(2, &GAME.replace(r#""state":"running""#, r#""state":"won""#));
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].starts_with("/state: "), "{}", errors[0]);
+
+        // a game where the header is expected
+        assert!(!check(1, GAME).is_empty());
+        let errors = check(2, "{\"score\":");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("invalid json: "), "{}", errors[0]);
+    }
+}
@@ -0,0 +1,280 @@
+//! A single frame of a recording (`snakepipe snapshot`), as plain text (the board drawn by `render`, without the
+//! escape sequences) or as an SVG image (with the colors of the animated exports)
+
+use std::io::Write;
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use crate::export::raster::{color, BACKGROUND, PALETTE, TEXT};
+use crate::input::{parse_gamestate_from, Frame, Game, GameState, Grid, InitOptions, ParseOptions};
+use crate::recording::input_name;
+use crate::render::{format_effects, prepare_grid, render_board, RenderGrid};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SnapshotFormat {
+    Txt,
+    Svg,
+}
+
+/// Which game of the recording
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tick {
+    /// index of the game, starting at 0
+    Index(usize),
+    Last,
+    /// the first game over
+    GameOver,
+}
+
+/// Parses a tick like `42`, `last` or `game-over`
+pub fn parse_tick(value: &str) -> Result<Tick, String> {
+    match value {
+        "last" => Ok(Tick::Last),
+        "game-over" => Ok(Tick::GameOver),
+        _ => value.parse().map(Tick::Index).map_err(|_| {
+            format!(
+                "`{}` is not a tick (index of the game, `last` or `game-over`)",
+                value
+            )
+        }),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotOptions {
+    pub tick: Tick,
+    pub format: SnapshotFormat,
+    /// in pixels (svg)
+    pub cell_size: u32,
+}
+
+fn prepared_grid(options: &InitOptions, game: &Game) -> RenderGrid {
+    let mut grid = RenderGrid::new(options.size.width, options.size.height, options.grid);
+    prepare_grid(&mut grid, game.clone());
+    grid
+}
+
+fn score_line(game: &Game) -> String {
+    format!(
+        "Score: {} - {}{}",
+        game.score,
+        game.state,
+        format_effects(&game.effects)
+    )
+}
+
+fn format_txt(options: &InitOptions, game: &Game) -> String {
+    let grid = prepared_grid(options, game);
+    let mut lines = render_board(&grid, options.size.width, false);
+    lines.push(score_line(game));
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+fn css_color(index: u8) -> String {
+    let [r, g, b] = PALETTE[index as usize];
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// One square per cell (odd rows shifted by half a cell on a hex grid), the score line below the board
+fn format_svg(options: &InitOptions, game: &Game, cell_size: u32) -> String {
+    let grid = prepared_grid(options, game);
+    let shift = match options.grid {
+        Grid::Square => 0,
+        Grid::Hex => cell_size / 2,
+    };
+    let font_size = cell_size.max(12);
+    let width = options.size.width * cell_size + shift;
+    let board_height = options.size.height * cell_size;
+    let height = board_height + font_size * 2;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n\
+         <rect width=\"{width}\" height=\"{height}\" fill=\"{}\"/>\n",
+        css_color(BACKGROUND)
+    );
+    // cells are separated by a line of background, unless too small
+    let size = if cell_size >= 4 {
+        cell_size - 1
+    } else {
+        cell_size
+    };
+    for (y, row) in grid.data.rows_iter().enumerate() {
+        let offset = if y % 2 == 1 { shift } else { 0 };
+        for (x, point) in row.enumerate() {
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{size}\" height=\"{size}\" fill=\"{}\"/>\n",
+                offset + x as u32 * cell_size,
+                y as u32 * cell_size,
+                css_color(color(point))
+            ));
+        }
+    }
+    svg.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" font-family=\"monospace\" font-size=\"{font_size}\" fill=\"{}\">{}</text>\n</svg>\n",
+        font_size / 2,
+        board_height + font_size * 3 / 2,
+        css_color(TEXT),
+        score_line(game)
+    ));
+    svg
+}
+
+/// The game at `tick`, along with the header of its session - or the number of games read when there is none
+fn pick(
+    options: InitOptions,
+    frames: impl Iterator<Item = Frame>,
+    tick: Tick,
+) -> Result<(InitOptions, Game), usize> {
+    let mut headers = vec![options];
+    // the latest game, along with the index of its session in `headers`
+    let mut last: Option<(usize, Game)> = None;
+    let mut ticks = 0;
+    for frame in frames {
+        let game = match frame {
+            Frame::Options(options) => {
+                headers.push(options);
+                continue;
+            }
+            Frame::Game(game) => game,
+        };
+        let session = headers.len() - 1;
+        let found = match tick {
+            Tick::Index(index) => index == ticks,
+            Tick::GameOver => game.state == GameState::Over,
+            Tick::Last => false,
+        };
+        ticks += 1;
+        if found {
+            return Ok((headers.swap_remove(session), game));
+        }
+        if tick == Tick::Last {
+            last = Some((session, game));
+        }
+    }
+    match last {
+        Some((session, game)) => Ok((headers.swap_remove(session), game)),
+        None => Err(ticks),
+    }
+}
+
+/// Reads the input until the game of `snapshot_options.tick` and outputs it
+pub fn run(
+    input_path: Option<&Path>,
+    snapshot_options: SnapshotOptions,
+    mut output: Box<dyn Write>,
+    parse_options: ParseOptions,
+) {
+    let input = match parse_gamestate_from(input_path, parse_options) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!(
+                "Error occurred while parsing {}: \"{}\"",
+                input_name(input_path),
+                e
+            );
+            std::process::exit(exitcode::DATAERR);
+        }
+    };
+    let (options, game) = match pick(input.options, input.lines, snapshot_options.tick) {
+        Ok(snapshot) => snapshot,
+        Err(ticks) => {
+            let reason = match snapshot_options.tick {
+                Tick::Index(index) => format!("no tick {} (only {} ticks)", index, ticks),
+                Tick::Last => "no games".to_string(),
+                Tick::GameOver => "the game is never over".to_string(),
+            };
+            eprintln!("Error: {} in {}", reason, input_name(input_path));
+            std::process::exit(exitcode::DATAERR);
+        }
+    };
+    let snapshot = match snapshot_options.format {
+        SnapshotFormat::Txt => format_txt(&options, &game),
+        SnapshotFormat::Svg => format_svg(&options, &game, snapshot_options.cell_size),
+    };
+    if let Err(e) = output
+        .write_all(snapshot.as_bytes())
+        .and_then(|_| output.flush())
+    {
+        eprintln!("Error occurred while writing: \"{}\"", e);
+        std::process::exit(exitcode::IOERR);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(frame_duration: u32) -> InitOptions {
+        serde_json::from_str(&format!(
+            r#"{{"frameDuration":{},"size":{{"width":6,"height":4}},"featuresWithVersion":{{}},"metadatas":{{}}}}"#,
+            frame_duration
+        ))
+        .unwrap()
+    }
+
+    fn game(score: u32, state: &str) -> Game {
+        serde_json::from_str(&format!(
+            r#"{{"snake":{{"direction":"right","head":{{"x":2,"y":1}},"tail":[{{"x":1,"y":1}}]}},"fruit":{{"x":4,"y":2}},"score":{},"state":"{}"}}"#,
+            score, state
+        ))
+        .unwrap()
+    }
+
+    /// Two sessions (frame durations 100 then 200), the first one ending with a game over, then a header without games
+    fn frames() -> Vec<Frame> {
+        vec![
+            Frame::Game(game(0, "running")),
+            Frame::Game(game(10, "running")),
+            Frame::Game(game(10, "over")),
+            Frame::Options(options(200)),
+            Frame::Game(game(0, "running")),
+            Frame::Game(game(20, "over")),
+            Frame::Options(options(300)),
+        ]
+    }
+
+    fn picked(tick: Tick) -> Result<(u32, u32, GameState), usize> {
+        pick(options(100), frames().into_iter(), tick)
+            .map(|(options, game)| (options.frame_duration, game.score, game.state))
+    }
+
+    #[test]
+    fn should_parse_the_ticks() {
+        assert_eq!(parse_tick("last"), Ok(Tick::Last));
+        assert_eq!(parse_tick("game-over"), Ok(Tick::GameOver));
+        assert_eq!(parse_tick("0"), Ok(Tick::Index(0)));
+        assert_eq!(parse_tick("42"), Ok(Tick::Index(42)));
+        for value in ["", "-1", "first", "Last", "4.2"] {
+            assert!(parse_tick(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn should_pick_the_game_with_the_header_of_its_session() {
+        assert_eq!(picked(Tick::Index(1)), Ok((100, 10, GameState::Running)));
+        assert_eq!(picked(Tick::Index(3)), Ok((200, 0, GameState::Running)));
+        assert_eq!(picked(Tick::Index(5)), Err(5));
+        assert_eq!(picked(Tick::Last), Ok((200, 20, GameState::Over)));
+        assert_eq!(picked(Tick::GameOver), Ok((100, 10, GameState::Over)));
+
+        let running = vec![Frame::Game(game(0, "running"))];
+        assert_eq!(
+            pick(options(100), running.into_iter(), Tick::GameOver).map(|_| ()),
+            Err(1)
+        );
+        assert_eq!(
+            pick(options(100), std::iter::empty(), Tick::Last).map(|_| ()),
+            Err(0)
+        );
+    }
+
+    #[test]
+    fn should_format_the_board_as_plain_text() {
+        let txt = format_txt(&options(100), &game(10, "running"));
+        assert!(!txt.contains('\u{1b}'), "{:?}", txt);
+        let lines: Vec<&str> = txt.lines().collect();
+        // the board, its borders and the score
+        assert_eq!(lines.len(), 4 + 2 + 1, "{}", txt);
+        assert_eq!(lines.last(), Some(&"Score: 10 - Running"));
+    }
+}
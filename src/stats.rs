@@ -0,0 +1,428 @@
+//! Statistics of a recording (`snakepipe stats`)
+//!
+//! Everything is derived from the games of the stream, the way the gamestate produces them:
+//! - a fruit is eaten when the score goes up
+//! - a game restarts when the score drops or when it is not over anymore
+//! - the cause of death is found by moving the head one more time on the frame where the game is over
+//!
+//! Without `elapsed` in the games, each tick is counted as the `frameDuration` of its session.
+
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+use crate::gamestate::physics;
+use crate::input::{
+    parse_gamestate_from, Direction, Frame, Game, GameState, Grid, InitOptions, ParseOptions,
+    Position,
+};
+use crate::recording::input_name;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Death {
+    /// ran into its own tail
+    Tail,
+    /// left the playable area (shrinking arena)
+    Wall,
+    /// ran into a snake controlled by the cpu
+    Rival,
+    Unknown,
+}
+
+impl fmt::Display for Death {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let str = match self {
+            Self::Tail => "Own tail",
+            Self::Wall => "Wall",
+            Self::Rival => "Rival",
+            Self::Unknown => "Unknown",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    /// in ms
+    pub duration: u64,
+    /// false if some of the durations were estimated from the `frameDuration` (no `elapsed` in the games)
+    pub timed: bool,
+    pub ticks: usize,
+    pub final_score: u32,
+    pub max_score: u32,
+    pub fruits_eaten: usize,
+    /// ticks while running (not paused nor over) per fruit eaten
+    pub ticks_per_fruit: Option<f64>,
+    pub turns: usize,
+    /// in ms
+    pub time_paused: u64,
+    pub restarts: usize,
+    /// one per game over, in order
+    pub deaths: Vec<Death>,
+}
+
+/// What the physics of the gamestate need to move the head on the board of the session
+fn board(options: &InitOptions, game: &Game) -> physics::Board {
+    let mut board = physics::Board::new(options.size.width, options.size.height);
+    if options.grid == Grid::Hex {
+        board.grid = physics::Grid::Hex;
+    }
+    board.portals = game
+        .portals
+        .iter()
+        .map(|portal| physics::Portal(position(&portal.0), position(&portal.1)))
+        .collect();
+    board
+}
+
+fn position(position: &Position) -> physics::Position {
+    physics::Position {
+        x: position.x,
+        y: position.y,
+    }
+}
+
+fn direction(direction: Direction) -> physics::Direction {
+    match direction {
+        Direction::Up => physics::Direction::Up,
+        Direction::Right => physics::Direction::Right,
+        Direction::Down => physics::Direction::Down,
+        Direction::Left => physics::Direction::Left,
+        Direction::UpLeft => physics::Direction::UpLeft,
+        Direction::UpRight => physics::Direction::UpRight,
+        Direction::DownLeft => physics::Direction::DownLeft,
+        Direction::DownRight => physics::Direction::DownRight,
+    }
+}
+
+/// Why the game is over: on that frame, the snake didn't move into where its head would go next
+fn death(options: &InitOptions, game: &Game) -> Death {
+    let board = board(options, game);
+    let mut next = position(&game.snake.head);
+    next.move_to_dir(direction(game.snake.direction));
+    let next = board.resolve(next);
+    let next = Position {
+        x: next.x,
+        y: next.y,
+    };
+    let is_inside = |position: &Position| {
+        game.bounds.is_none_or(|bounds| {
+            let (x, y) = options.grid.to_offset(position);
+            bounds.contains(x, y)
+        })
+    };
+    if game
+        .snake
+        .tail
+        .iter()
+        .any(|pos| *pos == next || *pos == game.snake.head)
+    {
+        Death::Tail
+    } else if !is_inside(&game.snake.head) || !is_inside(&next) {
+        Death::Wall
+    } else if game
+        .rivals
+        .iter()
+        .any(|rival| rival.snake.head == next || rival.snake.tail.contains(&next))
+    {
+        Death::Rival
+    } else {
+        Death::Unknown
+    }
+}
+
+/// Computes the statistics of the frames, `options` being the header of the first session
+pub fn compute(options: InitOptions, frames: impl Iterator<Item = Frame>) -> Stats {
+    let mut stats = Stats {
+        timed: true,
+        ..Stats::default()
+    };
+    let mut options = options;
+    let mut running_ticks = 0;
+    // previous game of the session
+    let mut previous: Option<Game> = None;
+    for frame in frames {
+        let game = match frame {
+            Frame::Options(new_options) => {
+                options = new_options;
+                previous = None;
+                continue;
+            }
+            Frame::Game(game) => game,
+        };
+        stats.ticks += 1;
+        stats.max_score = stats.max_score.max(game.score);
+        stats.final_score = game.score;
+        if game.state == GameState::Running {
+            running_ticks += 1;
+        }
+        if let Some(previous) = previous.as_ref() {
+            let gap = match (previous.elapsed, game.elapsed) {
+                (Some(previous_elapsed), Some(elapsed)) if elapsed >= previous_elapsed => {
+                    elapsed - previous_elapsed
+                }
+                _ => {
+                    stats.timed = false;
+                    options.frame_duration as u64
+                }
+            };
+            stats.duration += gap;
+            if previous.state == GameState::Paused {
+                stats.time_paused += gap;
+            }
+            if game.restarted_from(previous) {
+                stats.restarts += 1;
+            } else {
+                if game.score > previous.score {
+                    stats.fruits_eaten += 1;
+                }
+                if game.snake.direction != previous.snake.direction {
+                    stats.turns += 1;
+                }
+            }
+        }
+        let was_over = previous
+            .as_ref()
+            .is_some_and(|previous| previous.state == GameState::Over);
+        if game.state == GameState::Over && !was_over {
+            stats.deaths.push(death(&options, &game));
+        }
+        previous = Some(game);
+    }
+    if stats.fruits_eaten > 0 {
+        stats.ticks_per_fruit = Some(running_ticks as f64 / stats.fruits_eaten as f64);
+    }
+    stats
+}
+
+/// Duration like `1m 05.3s`
+fn format_duration(millis: u64) -> String {
+    let minutes = millis / 60_000;
+    let seconds = (millis % 60_000) as f64 / 1000.0;
+    if minutes > 0 {
+        format!("{}m {:04.1}s", minutes, seconds)
+    } else {
+        format!("{:.1}s", seconds)
+    }
+}
+
+fn format_table(stats: &Stats) -> String {
+    // estimated durations are flagged
+    let approximately = if stats.timed { "" } else { "~" };
+    let deaths = if stats.deaths.is_empty() {
+        "-".to_string()
+    } else {
+        stats
+            .deaths
+            .iter()
+            .map(|death| death.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let rows = [
+        (
+            "Duration",
+            format!("{}{}", approximately, format_duration(stats.duration)),
+        ),
+        ("Ticks", stats.ticks.to_string()),
+        ("Final score", stats.final_score.to_string()),
+        ("Max score", stats.max_score.to_string()),
+        ("Fruits eaten", stats.fruits_eaten.to_string()),
+        (
+            "Ticks per fruit",
+            stats
+                .ticks_per_fruit
+                .map_or("-".to_string(), |ticks| format!("{:.1}", ticks)),
+        ),
+        ("Turns", stats.turns.to_string()),
+        (
+            "Time paused",
+            format!("{}{}", approximately, format_duration(stats.time_paused)),
+        ),
+        ("Restarts", stats.restarts.to_string()),
+        ("Cause of death", deaths),
+    ];
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    rows.iter()
+        .map(|(label, value)| format!("{:<width$}  {}\n", label, value, width = width))
+        .collect()
+}
+
+/// Reads the whole input and prints its statistics, as a table or as JSON
+pub fn run(input_path: Option<&Path>, json: bool, parse_options: ParseOptions) {
+    let input = match parse_gamestate_from(input_path, parse_options) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!(
+                "Error occurred while parsing {}: \"{}\"",
+                input_name(input_path),
+                e
+            );
+            std::process::exit(exitcode::DATAERR);
+        }
+    };
+    let stats = compute(input.options, input.lines);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats).unwrap());
+    } else {
+        print!("{}", format_table(&stats));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = r#"{"frameDuration":120,"size":{"width":10,"height":10},"featuresWithVersion":{"gamestate":"snakepipe@2.2.1(rust)"},"metadatas":{}}"#;
+
+    fn options() -> InitOptions {
+        serde_json::from_str(HEADER).unwrap()
+    }
+
+    /// A game from its snake (head first) and the fields after the `score`
+    fn game(direction: &str, snake: &[(i32, i32)], score: u32, rest: &str) -> Frame {
+        let positions: Vec<String> = snake
+            .iter()
+            .map(|(x, y)| format!(r#"{{"x":{},"y":{}}}"#, x, y))
+            .collect();
+        Frame::Game(
+            serde_json::from_str(&format!(
+                r#"{{"snake":{{"direction":"{}","head":{},"tail":[{}]}},"fruit":{{"x":9,"y":9}},"score":{},{}}}"#,
+                direction,
+                positions[0],
+                positions[1..].join(","),
+                score,
+                rest
+            ))
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn should_count_fruits_turns_and_pauses_until_a_tail_death() {
+        let frames = vec![
+            game(
+                "right",
+                &[(2, 2), (1, 2)],
+                0,
+                r#""state":"running","elapsed":0"#,
+            ),
+            game(
+                "right",
+                &[(3, 2), (2, 2)],
+                0,
+                r#""state":"running","elapsed":100"#,
+            ),
+            game(
+                "down",
+                &[(3, 3), (3, 2), (2, 2)],
+                1,
+                r#""state":"running","elapsed":200"#,
+            ),
+            game(
+                "down",
+                &[(3, 3), (3, 2), (2, 2)],
+                1,
+                r#""state":"paused","elapsed":300"#,
+            ),
+            game(
+                "down",
+                &[(3, 4), (3, 3), (3, 2)],
+                1,
+                r#""state":"running","elapsed":1300"#,
+            ),
+            // turned back onto its own tail
+            game(
+                "up",
+                &[(3, 4), (3, 3), (3, 2)],
+                1,
+                r#""state":"over","elapsed":1400"#,
+            ),
+        ];
+        let stats = compute(options(), frames.into_iter());
+        assert_eq!(stats.duration, 1400);
+        assert!(stats.timed);
+        assert_eq!(stats.ticks, 6);
+        assert_eq!((stats.final_score, stats.max_score), (1, 1));
+        assert_eq!(stats.fruits_eaten, 1);
+        assert_eq!(stats.ticks_per_fruit, Some(4.0));
+        assert_eq!(stats.turns, 2);
+        assert_eq!(stats.time_paused, 1000);
+        assert_eq!(stats.restarts, 0);
+        assert_eq!(stats.deaths, [Death::Tail]);
+    }
+
+    #[test]
+    fn should_tell_a_wall_death() {
+        let bounds = r#""bounds":{"x":1,"y":1,"width":8,"height":8}"#;
+        let frames = vec![
+            game(
+                "right",
+                &[(7, 5), (6, 5)],
+                0,
+                &format!(r#""state":"running",{}"#, bounds),
+            ),
+            game(
+                "right",
+                &[(8, 5), (7, 5)],
+                0,
+                &format!(r#""state":"over",{}"#, bounds),
+            ),
+        ];
+        let stats = compute(options(), frames.into_iter());
+        assert_eq!(stats.deaths, [Death::Wall]);
+    }
+
+    #[test]
+    fn should_count_restarts_without_fruits_nor_turns() {
+        let frames = vec![
+            game(
+                "right",
+                &[(2, 2), (1, 2)],
+                0,
+                r#""state":"running","elapsed":0"#,
+            ),
+            game(
+                "right",
+                &[(3, 2), (2, 2)],
+                1,
+                r#""state":"running","elapsed":100"#,
+            ),
+            // restarted while running: the score drops
+            game(
+                "up",
+                &[(5, 5), (5, 6)],
+                0,
+                r#""state":"running","elapsed":200"#,
+            ),
+            game(
+                "up",
+                &[(5, 4), (5, 5)],
+                0,
+                r#""state":"running","elapsed":300"#,
+            ),
+        ];
+        let stats = compute(options(), frames.into_iter());
+        assert_eq!(stats.restarts, 1);
+        assert_eq!(stats.fruits_eaten, 1);
+        assert_eq!(stats.turns, 0);
+        assert_eq!((stats.final_score, stats.max_score), (0, 1));
+        assert!(stats.deaths.is_empty());
+    }
+
+    #[test]
+    fn should_estimate_the_durations_without_elapsed() {
+        let frames = vec![
+            game("right", &[(2, 2), (1, 2)], 0, r#""state":"running""#),
+            game("right", &[(3, 2), (2, 2)], 0, r#""state":"paused""#),
+            game("right", &[(4, 2), (3, 2)], 0, r#""state":"running""#),
+        ];
+        let stats = compute(options(), frames.into_iter());
+        assert!(!stats.timed);
+        assert_eq!(stats.duration, 240);
+        assert_eq!(stats.time_paused, 120);
+        assert_eq!(stats.ticks_per_fruit, None);
+    }
+}
@@ -16,13 +16,20 @@ pub async fn bootstrap(address: String) {
     let mut current_init_options: Option<InitOptions> = None;
     while let Some(event) = events.next().await {
         match event {
-            // what if the sse re-opens with different init_options ? we can't support for the moment - message parsers should ignore the header line
+            // if the sse re-opens, init_options may have changed: the header line is output again (it starts a new session)
             Ok(Event::Open) => {
                 if let Ok(init_options) = fetch_init_options(&address).await {
                     println!("{}", serde_json::to_string(&init_options).unwrap());
                     current_init_options = Some(init_options);
                 }
             }
+            // a new session started on the server
+            Ok(Event::Message(message)) if message.event == "options" => {
+                if let Ok(init_options) = serde_json::from_str::<InitOptions>(&message.data) {
+                    println!("{}", serde_json::to_string(&init_options).unwrap());
+                    current_init_options = Some(init_options);
+                }
+            }
             Ok(Event::Message(message)) => {
                 if current_init_options.is_some() {
                     if let Ok(game_state) = serde_json::from_str::<Game>(&message.data) {
@@ -0,0 +1,219 @@
+//! Replay of a recording controlled with the keyboard (`snakepipe throttle --interactive`)
+//!
+//! The whole recording is read before the replay starts, so that it can be stepped through in both directions.
+//! The keyboard is read from the terminal (not stdin, which may be the recording).
+
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{poll, read, Event, KeyCode, KeyEvent, KeyModifiers};
+
+use crate::input::{parse_gamestate_from, Frame, Game, InitOptions, ParseOptions};
+use crate::recording::input_name;
+use crate::throttle::{output, passthrough_options, Playback, MAX_SPEED, MIN_SPEED};
+
+/// How long to wait for a key while paused (there is no frame to output meanwhile)
+const PAUSED_POLL: Duration = Duration::from_secs(3600);
+
+/// The games of the recording, along with the header of their session
+struct Timeline {
+    headers: Vec<InitOptions>,
+    /// index of the header in `headers`, game
+    games: Vec<(usize, Game)>,
+}
+
+struct Player {
+    timeline: Timeline,
+    playback: Playback,
+    position: usize,
+    paused: bool,
+    /// header of the last game output (only output again when the session changes)
+    session: Option<usize>,
+    recording: Option<Box<dyn Write>>,
+}
+
+enum Action {
+    TogglePause,
+    Step(isize),
+    Speed(f64),
+    /// in tenth of the recording
+    Jump(usize),
+    Quit,
+    None,
+}
+
+fn action(event: Event) -> Action {
+    match event {
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('c'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        }) => Action::Quit,
+        Event::Key(KeyEvent { code, .. }) => match code {
+            KeyCode::Char(' ') => Action::TogglePause,
+            KeyCode::Left => Action::Step(-1),
+            KeyCode::Right => Action::Step(1),
+            KeyCode::Char('+') | KeyCode::Char('=') => Action::Speed(2.0),
+            KeyCode::Char('-') => Action::Speed(0.5),
+            KeyCode::Char(digit @ '0'..='9') => Action::Jump(digit as usize - '0' as usize),
+            KeyCode::Char('q') | KeyCode::Esc => Action::Quit,
+            _ => Action::None,
+        },
+        _ => Action::None,
+    }
+}
+
+impl Player {
+    /// Outputs the game at `position` (preceded by the header of its session if it changed)
+    fn show(&mut self, position: usize) -> std::io::Result<()> {
+        self.position = position;
+        let (session, game) = &self.timeline.games[position];
+        if self.session != Some(*session) {
+            output(&self.timeline.headers[*session], &mut self.recording)?;
+            self.session = Some(*session);
+        }
+        output(game, &mut self.recording)
+    }
+
+    /// Position `offset` frames away from the current one, wrapping around when looping
+    fn offset(&self, offset: isize) -> usize {
+        let len = self.timeline.games.len() as isize;
+        let position = self.position as isize + offset;
+        if self.playback.loop_infinite {
+            position.rem_euclid(len) as usize
+        } else {
+            position.clamp(0, len - 1) as usize
+        }
+    }
+
+    /// Time to wait before outputting the game that follows the current one
+    fn delay_to_next(&self) -> Duration {
+        let next = self.offset(1);
+        let (session, game) = &self.timeline.games[next];
+        let (current_session, current) = &self.timeline.games[self.position];
+        // the timing of the recording only applies to consecutive games of a session
+        let previous_elapsed = if next == self.position + 1 && session == current_session {
+            current.elapsed
+        } else {
+            None
+        };
+        self.playback.delay(
+            self.timeline.headers[*session].frame_duration,
+            previous_elapsed,
+            game.elapsed,
+        )
+    }
+
+    /// Plays until quit, returns an error if the output failed
+    fn play(&mut self) -> std::io::Result<()> {
+        self.show(0)?;
+        let mut deadline = Instant::now() + self.delay_to_next();
+        loop {
+            let timeout = if self.paused {
+                PAUSED_POLL
+            } else {
+                deadline.saturating_duration_since(Instant::now())
+            };
+            if poll(timeout)? {
+                match action(read()?) {
+                    Action::TogglePause => {
+                        self.paused = !self.paused;
+                        deadline = Instant::now() + self.delay_to_next();
+                    }
+                    Action::Step(offset) => {
+                        // stepping is done frame by frame
+                        self.paused = true;
+                        self.show(self.offset(offset))?;
+                    }
+                    Action::Speed(factor) => {
+                        self.playback.speed =
+                            (self.playback.speed * factor).clamp(MIN_SPEED, MAX_SPEED);
+                        deadline = Instant::now() + self.delay_to_next();
+                    }
+                    Action::Jump(tenth) => {
+                        self.show(self.timeline.games.len() * tenth / 10)?;
+                        deadline = Instant::now() + self.delay_to_next();
+                    }
+                    Action::Quit => return Ok(()),
+                    Action::None => {}
+                }
+                continue;
+            }
+            if self.paused {
+                continue;
+            }
+            let next = self.offset(1);
+            if next == self.position {
+                // end of the recording (not looping): wait for the keyboard
+                self.paused = true;
+                continue;
+            }
+            self.show(next)?;
+            let delay = self.delay_to_next();
+            deadline += delay;
+            let now = Instant::now();
+            if now > deadline + delay {
+                deadline = now;
+            }
+        }
+    }
+}
+
+/// Reads the whole input, then replays it according to the keyboard:
+/// - space: pause/resume
+/// - left/right arrows: step one frame back/forward
+/// - +/-: double/halve the speed
+/// - 1 to 9: jump to 10% … 90% of the recording (0 to the beginning)
+/// - q, esc or ctrl+c: quit
+pub fn run(
+    playback: Playback,
+    input_path: Option<&Path>,
+    recording: Option<Box<dyn Write>>,
+    parse_options: ParseOptions,
+) {
+    let input = match parse_gamestate_from(input_path, parse_options) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!(
+                "Error occurred while parsing {}: \"{}\"",
+                input_name(input_path),
+                e
+            );
+            std::process::exit(exitcode::DATAERR);
+        }
+    };
+    let mut timeline = Timeline {
+        headers: vec![passthrough_options(&input.options, playback.frame_duration)],
+        games: Vec::new(),
+    };
+    for frame in input.lines {
+        match frame {
+            Frame::Options(options) => timeline
+                .headers
+                .push(passthrough_options(&options, playback.frame_duration)),
+            Frame::Game(game) => timeline.games.push((timeline.headers.len() - 1, game)),
+        }
+    }
+    if timeline.games.is_empty() {
+        eprintln!("Nothing to replay in {}", input_name(input_path));
+        std::process::exit(exitcode::DATAERR);
+    }
+    let mut player = Player {
+        timeline,
+        playback,
+        position: 0,
+        paused: false,
+        session: None,
+        recording,
+    };
+    let _ = crossterm::terminal::enable_raw_mode();
+    let result = player.play();
+    let _ = crossterm::terminal::disable_raw_mode();
+    // the recording is only complete once dropped (compressed files)
+    drop(player);
+    if let Err(e) = result {
+        eprintln!("Error occurred while writing: \"{}\"", e);
+        std::process::exit(exitcode::IOERR);
+    }
+}
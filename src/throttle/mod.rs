@@ -0,0 +1,330 @@
+pub mod interactive;
+mod rewind;
+
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::common::format_version_to_display;
+use crate::input::{parse_gamestate_from, Frame, InitOptions, ParseOptions};
+use crate::recording::input_name;
+use crate::throttle::rewind::Rewind;
+
+/// Bounds of the speed, as changed with +/- in interactive mode
+const MIN_SPEED: f64 = 1.0 / 16.0;
+const MAX_SPEED: f64 = 16.0;
+
+/// How the frames are replayed
+#[derive(Debug, Clone, Copy)]
+pub struct Playback {
+    /// in ms, at normal speed - forces a fixed frame rate, otherwise the timing of the recording is reproduced
+    pub frame_duration: Option<u32>,
+    /// in ms, longer pauses of the recording are shortened to this
+    pub max_pause: Option<u32>,
+    /// 2.0 replays twice as fast, 0.5 twice as slow
+    pub speed: f64,
+    /// start over at the beginning of the stream when it ends
+    pub loop_infinite: bool,
+}
+
+impl Playback {
+    /// Time to wait before outputting a game, at the current speed
+    ///
+    /// - `frame_duration`: the one of the session, used when the games carry no timing
+    /// - `previous_elapsed`/`elapsed`: timing of the previous game of the session and of this one
+    pub(crate) fn delay(
+        &self,
+        frame_duration: u32,
+        previous_elapsed: Option<u64>,
+        elapsed: Option<u64>,
+    ) -> Duration {
+        let millis = match (self.frame_duration, previous_elapsed, elapsed) {
+            (Some(frame_duration), _, _) => frame_duration as u64,
+            (None, Some(previous_elapsed), Some(elapsed)) if elapsed >= previous_elapsed => {
+                let gap = elapsed - previous_elapsed;
+                self.max_pause
+                    .map_or(gap, |max_pause| gap.min(max_pause as u64))
+            }
+            _ => frame_duration as u64,
+        };
+        Duration::from_secs_f64(millis as f64 / 1000.0 / self.speed)
+    }
+}
+
+/// Parses a speed like `2`, `0.5x` or `2x`
+pub fn parse_speed(value: &str) -> Result<f64, String> {
+    let speed: f64 = value
+        .trim_end_matches(['x', 'X'])
+        .parse()
+        .map_err(|_| format!("`{}` is not a speed (like 0.5x, 1x or 2x)", value))?;
+    if (MIN_SPEED..=MAX_SPEED).contains(&speed) {
+        Ok(speed)
+    } else {
+        Err(format!(
+            "speed must be between {}x and {}x",
+            MIN_SPEED, MAX_SPEED
+        ))
+    }
+}
+
+/// The header to output for a session: `frame_duration` overriden (if forced), `throttle` added to the features
+fn passthrough_options(options: &InitOptions, frame_duration: Option<u32>) -> InitOptions {
+    let mut options_passthrough = options.clone();
+    if let Some(frame_duration) = frame_duration {
+        options_passthrough.frame_duration = frame_duration;
+    }
+    options_passthrough
+        .features_with_version
+        .insert("throttle".to_string(), format_version_to_display());
+    options_passthrough
+        .metadatas
+        .insert("throttled".to_string(), "on".to_string());
+    options_passthrough
+}
+
+/// Outputs `value` on stdout, also writing it to the `recording` file if any
+fn output<T: Serialize>(value: &T, recording: &mut Option<Box<dyn Write>>) -> std::io::Result<()> {
+    let line = serde_json::to_string(value).unwrap();
+    writeln!(std::io::stdout(), "{}\r", line)?;
+    if let Some(file) = recording {
+        writeln!(file, "{}", line)?;
+    }
+    Ok(())
+}
+
+fn exit_on_output_error(result: std::io::Result<()>) {
+    if let Err(e) = result {
+        eprintln!("Error occurred while writing: \"{}\"\r", e);
+        std::process::exit(exitcode::IOERR);
+    }
+}
+
+/// Outputs the games of the input one after the other, waiting between them as they were recorded
+/// (or each `frame_duration` if forced)
+pub fn run(
+    playback: Playback,
+    input_path: Option<&Path>,
+    mut recording: Option<Box<dyn Write>>,
+    parse_options: ParseOptions,
+) {
+    match parse_gamestate_from(input_path, parse_options) {
+        Ok(input) => {
+            // ctrl+c (the only way out of a loop) stops before the next game, so that the recording gets completed
+            let stopped = Arc::new(AtomicBool::new(false));
+            if recording.is_some() {
+                let stopped = stopped.clone();
+                ctrlc::set_handler(move || stopped.store(true, Ordering::SeqCst))
+                    .expect("Could not send signal on channel.");
+            }
+            let options_passthrough = passthrough_options(&input.options, playback.frame_duration);
+            exit_on_output_error(output(&options_passthrough, &mut recording));
+            let mut frames = input.lines;
+            // only needed when looping: how to read the frames again once the input ended
+            let mut rewind = playback
+                .loop_infinite
+                .then(|| Rewind::new(input_path, parse_options));
+            let mut first_pass = true;
+            // when looping, the first session needs its header back if others sessions followed
+            let mut sessions = 1;
+            // nothing to loop over if the stream only had headers
+            let mut games_in_pass = 0;
+            // frame duration of the current session, timing of the last game output
+            let mut frame_duration = options_passthrough.frame_duration;
+            let mut previous_elapsed: Option<u64> = None;
+            let mut deadline = Instant::now();
+            loop {
+                // headers of new sessions are output along with their first game, only the games are throttled
+                let mut headers: Vec<InitOptions> = Vec::new();
+                let game = loop {
+                    let frame = match frames.next() {
+                        Some(frame) => frame,
+                        None => {
+                            let rewind = match rewind.as_mut() {
+                                Some(rewind) if games_in_pass > 0 => rewind,
+                                _ => {
+                                    // the recording is only complete once dropped (compressed files)
+                                    drop(recording);
+                                    std::process::exit(0);
+                                }
+                            };
+                            frames = rewind.replay().unwrap_or_else(|e| {
+                                eprintln!(
+                                    "Error occurred while rewinding {}: \"{}\"\r",
+                                    input_name(input_path),
+                                    e
+                                );
+                                std::process::exit(exitcode::IOERR);
+                            });
+                            first_pass = false;
+                            games_in_pass = 0;
+                            previous_elapsed = None;
+                            if sessions > 1 {
+                                frame_duration = options_passthrough.frame_duration;
+                                headers.push(options_passthrough.clone());
+                            }
+                            continue;
+                        }
+                    };
+                    if first_pass {
+                        if let Some(rewind) = rewind.as_mut() {
+                            if let Err(e) = rewind.push(&frame) {
+                                eprintln!("Error occurred while buffering: \"{}\"\r", e);
+                                std::process::exit(exitcode::IOERR);
+                            }
+                        }
+                    }
+                    match frame {
+                        Frame::Options(options) => {
+                            if first_pass {
+                                sessions += 1;
+                            }
+                            let options = passthrough_options(&options, playback.frame_duration);
+                            frame_duration = options.frame_duration;
+                            previous_elapsed = None;
+                            headers.push(options);
+                        }
+                        Frame::Game(game) => {
+                            games_in_pass += 1;
+                            break game;
+                        }
+                    }
+                };
+                // deadlines are absolute, so the time spent reading/writing doesn't make the frame rate drift
+                let delay = playback.delay(frame_duration, previous_elapsed, game.elapsed);
+                previous_elapsed = game.elapsed;
+                deadline += delay;
+                let now = Instant::now();
+                if deadline > now {
+                    thread::sleep(deadline - now);
+                } else if now - deadline > delay {
+                    // fell behind (input slower than the frame rate): start over from now rather than bursting the late frames
+                    deadline = now;
+                }
+                if stopped.load(Ordering::SeqCst) {
+                    // the recording is only complete once dropped (compressed files)
+                    drop(recording);
+                    std::process::exit(130);
+                }
+                for header in headers {
+                    exit_on_output_error(output(&header, &mut recording));
+                }
+                exit_on_output_error(output(&game, &mut recording));
+            }
+        }
+        Err(e) => {
+            eprintln!(
+                "Error occurred while parsing {}: \"{}\"",
+                input_name(input_path),
+                e
+            );
+            std::process::exit(exitcode::DATAERR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playback(frame_duration: Option<u32>, max_pause: Option<u32>, speed: f64) -> Playback {
+        Playback {
+            frame_duration,
+            max_pause,
+            speed,
+            loop_infinite: false,
+        }
+    }
+
+    #[test]
+    fn should_wait_the_recorded_gaps() {
+        let playback = playback(None, None, 1.0);
+        assert_eq!(
+            playback.delay(100, Some(1000), Some(1250)),
+            Duration::from_millis(250)
+        );
+        assert_eq!(
+            playback.delay(100, Some(1000), Some(61000)),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn should_clamp_the_pauses() {
+        let playback = playback(None, Some(2000), 1.0);
+        assert_eq!(
+            playback.delay(100, Some(1000), Some(61000)),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            playback.delay(100, Some(1000), Some(1250)),
+            Duration::from_millis(250)
+        );
+    }
+
+    #[test]
+    fn should_scale_with_the_speed() {
+        assert_eq!(
+            playback(None, None, 2.0).delay(100, Some(1000), Some(1250)),
+            Duration::from_millis(125)
+        );
+        assert_eq!(
+            playback(None, Some(2000), 0.5).delay(100, Some(0), Some(10000)),
+            Duration::from_secs(4)
+        );
+        assert_eq!(
+            playback(Some(40), None, 4.0).delay(100, Some(0), Some(10000)),
+            Duration::from_millis(10)
+        );
+    }
+
+    #[test]
+    fn should_fall_back_to_the_header_duration() {
+        let playback = playback(None, None, 1.0);
+        let header = Duration::from_millis(100);
+        assert_eq!(playback.delay(100, None, Some(1250)), header);
+        assert_eq!(playback.delay(100, Some(1000), None), header);
+        assert_eq!(playback.delay(100, None, None), header);
+        // the timestamps go back in time (new recording spliced in)
+        assert_eq!(playback.delay(100, Some(1250), Some(1000)), header);
+    }
+
+    #[test]
+    fn should_prefer_the_forced_duration() {
+        assert_eq!(
+            playback(Some(40), Some(10), 1.0).delay(100, Some(1000), Some(1250)),
+            Duration::from_millis(40)
+        );
+    }
+
+    #[test]
+    fn should_parse_the_speeds() {
+        assert_eq!(parse_speed("2"), Ok(2.0));
+        assert_eq!(parse_speed("0.5x"), Ok(0.5));
+        assert_eq!(parse_speed("4X"), Ok(4.0));
+        assert_eq!(parse_speed("0.0625x"), Ok(MIN_SPEED));
+        assert_eq!(parse_speed("16x"), Ok(MAX_SPEED));
+    }
+
+    #[test]
+    fn should_reject_the_speeds_out_of_bounds() {
+        for value in ["0.06x", "16.5x", "0", "-1x"] {
+            assert_eq!(
+                parse_speed(value),
+                Err("speed must be between 0.0625x and 16x".to_string()),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn should_reject_the_malformed_speeds() {
+        for value in ["", "x", "fast", "2xx2", "1/2"] {
+            assert!(parse_speed(value).is_err(), "{}", value);
+        }
+    }
+}
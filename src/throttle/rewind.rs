@@ -0,0 +1,170 @@
+//! Where the frames come from when the replay starts over (`snakepipe throttle --loop-infinite`)
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Result, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::input::{parse_gamestate_from, Frame, ParseOptions};
+
+/// Number of frames kept in memory, the following ones are buffered in a temp file
+const SPILL_THRESHOLD: usize = 10_000;
+
+pub enum Rewind {
+    /// The input file is read again from the start (nothing is buffered)
+    Reopen {
+        path: PathBuf,
+        parse_options: ParseOptions,
+    },
+    /// Stdin (or a pipe) can't be read twice: the frames are buffered while read the first time
+    Buffer {
+        frames: Vec<Frame>,
+        /// once there are more than [`SPILL_THRESHOLD`] frames, all of them go there (as NDJSON)
+        spill: Option<BufWriter<File>>,
+    },
+}
+
+impl Rewind {
+    pub fn new(input_path: Option<&Path>, parse_options: ParseOptions) -> Self {
+        match input_path {
+            Some(path) if path.metadata().is_ok_and(|metadata| metadata.is_file()) => {
+                Self::Reopen {
+                    path: path.to_path_buf(),
+                    parse_options,
+                }
+            }
+            _ => Self::Buffer {
+                frames: Vec::new(),
+                spill: None,
+            },
+        }
+    }
+
+    /// Keeps `frame` for the next loops (only needed while reading the input the first time)
+    pub fn push(&mut self, frame: &Frame) -> Result<()> {
+        if let Self::Buffer { frames, spill } = self {
+            match spill {
+                Some(file) => write_frame(file, frame)?,
+                None => {
+                    frames.push(frame.clone());
+                    if frames.len() > SPILL_THRESHOLD {
+                        let mut file = BufWriter::new(tempfile::tempfile()?);
+                        for frame in frames.drain(..) {
+                            write_frame(&mut file, &frame)?;
+                        }
+                        *spill = Some(file);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The frames from the start, without the first header (like [`crate::input::Input::lines`])
+    pub fn replay(&mut self) -> Result<Box<dyn Iterator<Item = Frame>>> {
+        match self {
+            Self::Reopen {
+                path,
+                parse_options,
+            } => parse_gamestate_from(Some(path), *parse_options)
+                .map(|input| input.lines)
+                .map_err(|e| std::io::Error::other(e.to_string())),
+            Self::Buffer {
+                spill: Some(file), ..
+            } => {
+                file.flush()?;
+                // the clone shares the position in the file, nothing is written anymore
+                let mut reader = file.get_ref().try_clone()?;
+                reader.seek(SeekFrom::Start(0))?;
+                Ok(Box::new(
+                    BufReader::new(reader)
+                        .lines()
+                        .map_while(|line| line.ok())
+                        .filter_map(|line| Frame::parse(&line).ok()),
+                ))
+            }
+            Self::Buffer { frames, .. } => Ok(Box::new(frames.clone().into_iter())),
+        }
+    }
+}
+
+fn write_frame<W: Write>(writer: &mut W, frame: &Frame) -> Result<()> {
+    serde_json::to_writer(&mut *writer, frame)?;
+    writeln!(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTIONS: &str = r#"{"frameDuration":100,"size":{"width":10,"height":10},"featuresWithVersion":{},"metadatas":{}}"#;
+
+    fn frames(count: usize) -> Vec<Frame> {
+        (0..count)
+            .map(|score| {
+                Frame::parse(&format!(
+                    r#"{{"snake":{{"direction":"right","head":{{"x":1,"y":1}},"tail":[]}},"fruit":{{"x":9,"y":9}},"score":{},"state":"running"}}"#,
+                    score
+                ))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    fn to_lines(frames: impl IntoIterator<Item = Frame>) -> Vec<String> {
+        frames
+            .into_iter()
+            .map(|frame| serde_json::to_string(&frame).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn should_reopen_the_files() {
+        let frames = frames(3);
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "{}", OPTIONS).unwrap();
+        for frame in &frames {
+            write_frame(&mut file, frame).unwrap();
+        }
+        file.flush().unwrap();
+        let mut rewind = Rewind::new(Some(file.path()), ParseOptions::default());
+        assert!(matches!(rewind, Rewind::Reopen { .. }));
+        for frame in &frames {
+            rewind.push(frame).unwrap();
+        }
+        for _ in 0..2 {
+            assert_eq!(to_lines(rewind.replay().unwrap()), to_lines(frames.clone()));
+        }
+    }
+
+    #[test]
+    fn should_buffer_the_frames_in_memory() {
+        let frames = frames(3);
+        let mut rewind = Rewind::new(None, ParseOptions::default());
+        for frame in &frames {
+            rewind.push(frame).unwrap();
+        }
+        assert!(matches!(rewind, Rewind::Buffer { spill: None, .. }));
+        for _ in 0..2 {
+            assert_eq!(to_lines(rewind.replay().unwrap()), to_lines(frames.clone()));
+        }
+    }
+
+    #[test]
+    fn should_spill_the_frames_past_the_threshold() {
+        let frames = frames(SPILL_THRESHOLD + 10);
+        let mut rewind = Rewind::new(None, ParseOptions::default());
+        for frame in &frames {
+            rewind.push(frame).unwrap();
+        }
+        match &rewind {
+            Rewind::Buffer {
+                frames,
+                spill: Some(_),
+            } => assert!(frames.is_empty()),
+            _ => panic!("the frames should be in the temp file"),
+        }
+        for _ in 0..2 {
+            assert_eq!(to_lines(rewind.replay().unwrap()), to_lines(frames.clone()));
+        }
+    }
+}
@@ -67,7 +67,7 @@ function getQrcodeUrlToDisplay(renderBrowserHost) {
 
 /**
  *
- * @param {(eventName: 'connected' | 'event', payload: any) => void} cb
+ * @param {(eventName: 'connected' | 'options' | 'event', payload: any) => void} cb
  */
 async function bootstrap(cb) {
   const events = new EventSource("/events");
@@ -84,6 +84,10 @@ async function bootstrap(cb) {
       cb('event', JSON.parse(event.data));
     }
   }
+  // a new session started (new size, new options ...)
+  events.addEventListener('options', (event) => {
+    cb('options', JSON.parse(event.data));
+  });
 }
 
 /**
@@ -166,7 +170,11 @@ async function prepareGame() {
   function process(eventName, payload) {
     switch (eventName) {
       case 'connected':
-        console.log("connected", payload);
+      case 'options':
+        console.log(eventName, payload);
+        if (currentCleanupFunction) {
+          currentCleanupFunction();
+        }
         currentInitOptions = payload;
         gameNode = prepareRootNode('ready', rootNode, getRendererName());
         const { cleanup, context } = currentRenderer.setup(currentInitOptions, gameNode);
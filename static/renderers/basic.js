@@ -2,6 +2,22 @@ import { makeRenderInfos } from './utils.js'
 
 const renderInfos = makeRenderInfos(["score", "version"]);
 
+/**
+ * Writes `char` in the cell where `position` is drawn (hex grids use axial coordinates, drawn in "odd-r" layout)
+ *
+ * @param {string[][]} buffer
+ * @param {import("../types").InitOptions} initOptions
+ * @param {import("../types").Position} position
+ * @param {string} char
+ */
+function setCell(buffer, initOptions, position, char) {
+  const x = initOptions.grid === 'hex' ? position.x + (position.y - (position.y & 1)) / 2 : position.x;
+  // snakes spawn with their tail partly outside of the board
+  if (buffer[position.y] && buffer[position.y][x]) {
+    buffer[position.y][x] = char;
+  }
+}
+
 /**
  * Basic render function
  *
@@ -12,12 +28,44 @@ export function renderFrame(initOptions, frameInfos, context) {
   for (let i = 0; i < initOptions.size.height; i++) {
     buffer.push(Array.from({ length: initOptions.size.width }, () => '·'));
   }
-  buffer[frameInfos.snake.head.y][frameInfos.snake.head.x] = 'H';
-  buffer[frameInfos.fruit.y][frameInfos.fruit.x] = 'F';
+  if (frameInfos.bounds) {
+    const { x, y, width, height } = frameInfos.bounds;
+    buffer.forEach((row, rowIndex) => row.forEach((_, colIndex) => {
+      if (colIndex < x || rowIndex < y || colIndex >= x + width || rowIndex >= y + height) {
+        row[colIndex] = '░';
+      }
+    }));
+  }
+  (frameInfos.portals || []).forEach(portal => {
+    portal.forEach(end => {
+      setCell(buffer, initOptions, end, 'O');
+    });
+  });
+  // the snake of a previous run, under everything else
+  if (frameInfos.ghost) {
+    frameInfos.ghost.tail.forEach(tailFragment => {
+      setCell(buffer, initOptions, tailFragment, 't');
+    });
+    setCell(buffer, initOptions, frameInfos.ghost.head, 'h');
+  }
+  (frameInfos.rivals || []).forEach(rival => {
+    rival.tail.forEach(tailFragment => {
+      setCell(buffer, initOptions, tailFragment, 'r');
+    });
+    setCell(buffer, initOptions, rival.head, 'R');
+  });
+  if (frameInfos.powerUp) {
+    const { kind, position } = frameInfos.powerUp;
+    setCell(buffer, initOptions, position, { "slow-motion": 'S', "ghost": 'G', "magnet": 'M' }[kind]);
+  }
+  setCell(buffer, initOptions, frameInfos.snake.head, 'H');
+  setCell(buffer, initOptions, frameInfos.fruit, 'F');
   frameInfos.snake.tail.forEach(tailFragment => {
-    buffer[tailFragment.y][tailFragment.x] = 'T';
+    setCell(buffer, initOptions, tailFragment, 'T');
   });
-  const rendered = buffer.map(row => `${row.join('')}`).join('\r\n');
+  const rendered = initOptions.grid === 'hex'
+    ? buffer.map((row, rowIndex) => `${rowIndex % 2 ? ' ' : ''}${row.join(' ')}`).join('\r\n')
+    : buffer.map(row => `${row.join('')}`).join('\r\n');
   context.preNode.textContent = rendered;
   renderInfos(initOptions, frameInfos, context.infosNode);
 }
@@ -78,7 +126,7 @@ export function setup(initOptions, rootNode) {
   const infosNode = document.createElement('ul');
   infosNode.id = "basic-infos";
   rootNode.appendChild(infosNode);
-  preNode.style.width = `${initOptions.size.width}ch`;
+  preNode.style.width = `${initOptions.grid === 'hex' ? initOptions.size.width * 2 : initOptions.size.width}ch`;
   preNode.style.height = `calc(${preNode.style.lineHeight}*${initOptions.size.height})`;
   return {
     context: {
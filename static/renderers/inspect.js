@@ -10,12 +10,21 @@ const renderInfos = makeRenderInfos(["version"]);
 export function renderFrame(initOptions, frameInfos, context) {
   context.gameNode.innerHTML = `
     <li>State: ${frameInfos.state}</li>
-    <li>Score: ${frameInfos.score}</li>
-    <li>Fruit: x: ${frameInfos.fruit.x} / y: ${frameInfos.fruit.y}</li>
+    <li>Score: ${frameInfos.score}</li>${frameInfos.powerUp ? `
+    <li>Power-up: ${frameInfos.powerUp.kind} - x: ${frameInfos.powerUp.position.x} / y: ${frameInfos.powerUp.position.y}</li>` : ''}${frameInfos.effects ? `
+    <li>Effects: ${frameInfos.effects.map(({ kind, remaining }) => `${kind} (${remaining})`).join(' / ')}</li>` : ''}
+    <li>Fruit: x: ${frameInfos.fruit.x} / y: ${frameInfos.fruit.y}</li>${frameInfos.bounds ? `
+    <li>Bounds: x: ${frameInfos.bounds.x} / y: ${frameInfos.bounds.y} / width: ${frameInfos.bounds.width} / height: ${frameInfos.bounds.height}</li>` : ''}${frameInfos.portals ? `
+    <li>Portals:<ul>${frameInfos.portals.map(([a, b]) => {
+    return `<li>x: ${a.x} / y: ${a.y} ⇄ x: ${b.x} / y: ${b.y}</li>`
+  }).join('')}</ul></li>` : ''}
     <li>Snake Head: x: ${frameInfos.snake.head.x} / y: ${frameInfos.snake.head.y}</li>
     <li>Snake Tail:<ul>${frameInfos.snake.tail.map(item => {
     return `<li>x: ${item.x} / y: ${item.y}</li>`
-  }).join('')}</ul></li>`
+  }).join('')}</ul></li>${frameInfos.rivals ? `
+    <li>Rivals:<ul>${frameInfos.rivals.map(rival => {
+    return `<li>${rival.personality} - head: x: ${rival.head.x} / y: ${rival.head.y} - length: ${rival.tail.length + 1}</li>`
+  }).join('')}</ul></li>` : ''}`
   renderInfos(initOptions, frameInfos, context.infosNode);
 }
 
@@ -12,13 +12,21 @@ export function makeRenderInfos(features) {
    */
   return function renderInfos(initOptions, frameInfos, infosNode) {
     const infos = [
-      features.includes("score") ? `<li>Score: ${frameInfos.score} - ${frameInfos.state}</li>` : false,
+      features.includes("score") ? `<li>Score: ${frameInfos.score} - ${frameInfos.state}${makeEffects(frameInfos.effects)}</li>` : false,
       features.includes("version") ? `<li>${makeVersion(initOptions.featuresWithVersion)}</li>` : false,
     ].filter(Boolean);
     infosNode.innerHTML = infos.join('');
   }
 }
 
+/**
+ * Active power-ups with the number of ticks left (same as in `src/render.rs`)
+ * @param {import("../types").Effect[] | undefined} effects
+ */
+export function makeEffects(effects = []) {
+  return effects.map(({ kind, remaining }) => ` - ${kind} (${remaining})`).join('');
+}
+
 /**
  * Same implementation as in `src/common.rs`
  * @param {Record<string, string>} featuresWithVersion
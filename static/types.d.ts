@@ -5,32 +5,110 @@ export type Size = {
 
 export type GameState = "paused" | "over" | "running";
 
+export type Position = {
+  x: number
+  y: number
+}
+
+/**
+ * Pair of cells teleporting the head of the snake from one to the other
+ */
+export type Portal = [Position, Position]
+
+/**
+ * Playable area (only present in shrinking arena mode) - the cells outside are walls
+ */
+export type Bounds = {
+  x: number
+  y: number
+  width: number
+  height: number
+}
+
+/**
+ * `upleft`, `upright`, `downleft` and `downright` are only used on hex grids
+ * (which don't use `up` and `down`)
+ */
+export type Direction = "up" | "right" | "down" | "left" | "upleft" | "upright" | "downleft" | "downright"
+
+export type Snake = {
+  direction: Direction
+  head: Position
+  tail: Position[]
+}
+
+/**
+ * Snake controlled by the cpu, competing for the fruit
+ */
+export type Rival = Snake & {
+  personality: "greedy" | "cautious" | "random"
+}
+
+export type PowerUpKind = "slow-motion" | "ghost" | "magnet"
+
+/**
+ * Power-up lying on the board, waiting to be picked up
+ */
+export type PowerUp = {
+  kind: PowerUpKind
+  position: Position
+}
+
+/**
+ * Power-up that was picked up and is still active (`remaining` is in ticks)
+ */
+export type Effect = {
+  kind: PowerUpKind
+  remaining: number
+}
+
 export type Game = {
-  snake: {
-    direction: string
-    head: {
-      x: number
-      y: number
-    }
-    tail: {
-      x: number
-      y: number
-    }[]
-  }
-  fruit: {
-    x: number
-    y: number
-  }
+  snake: Snake
+  fruit: Position
   score: number
   state: GameState
+  bounds?: Bounds
+  portals?: Portal[]
+  rivals?: Rival[]
+  powerUp?: PowerUp
+  effects?: Effect[]
+  /**
+   * Snake of a previous run replayed in lockstep (`gamestate --ghost`), nothing collides with it
+   */
+  ghost?: Snake
+  /**
+   * ms since the start of the game (used by `throttle` to reproduce the original timing)
+   */
+  elapsed?: number
 }
 
+/**
+ * On hex grids, the positions are axial coordinates (`x` is q, `y` is r),
+ * drawn in rows of hexagons with odd rows shifted by half a cell ("odd-r" layout).
+ * `bounds` are still expressed in columns/rows.
+ */
+export type Grid = "square" | "hex"
+
 export type InitOptions = {
   frameDuration: number
+  /**
+   * Options of `gamestate`, recorded so that a run can be raced again (`gamestate --ghost`)
+   */
+  snakeLength?: number
+  shrinkEvery?: number
+  portals?: number
+  mapPortals?: Portal[]
+  rivals?: number
+  powerUps?: boolean
   size: {
     width: number
     height: number
   }
+  grid?: Grid
+  /**
+   * Seed of the random placements of `gamestate`: the same seed gives the same board
+   */
+  seed?: number
   featuresWithVersion: Record<string, string>
   metadatas: Record<string, string>
 }